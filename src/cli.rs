@@ -1,5 +1,66 @@
-use clap::{Arg, Command, Parser};
-use std::path::PathBuf;
+use crate::models::code_types::{CloneType, Engine, Granularity};
+use crate::models::report::ReportOptions;
+use crate::utils::config::{find_config, load_config, Config, Overrides};
+use crate::utils::quality_gate::QualityGate;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command, Parser};
+use std::path::{Path, PathBuf};
+
+/// Exit code when a `--fail-on-*` limit is exceeded
+pub const EXIT_GATE_FAILED: i32 = 1;
+/// Exit code for invalid arguments and I/O errors, the same code clap uses for usage errors
+pub const EXIT_ERROR: i32 = 2;
+
+/// Parse a near-miss similarity threshold, which must lie in (0, 1]
+fn parse_similarity(value: &str) -> Result<f64, String> {
+    let similarity: f64 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    check_similarity(similarity)
+}
+
+fn check_similarity(similarity: f64) -> Result<f64, String> {
+    if similarity > 0.0 && similarity <= 1.0 {
+        Ok(similarity)
+    } else {
+        Err(format!(
+            "similarity must be greater than 0 and at most 1, got {}",
+            similarity
+        ))
+    }
+}
+
+/// Parse a duplication limit in percent, which must lie in [0, 100]
+fn parse_percentage(value: &str) -> Result<f64, String> {
+    let percentage: f64 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    check_percentage(percentage)
+}
+
+fn check_percentage(percentage: f64) -> Result<f64, String> {
+    if (0.0..=100.0).contains(&percentage) {
+        Ok(percentage)
+    } else {
+        Err(format!(
+            "percentage must be between 0 and 100, got {}",
+            percentage
+        ))
+    }
+}
+
+/// Whether the option was given on the command line rather than left at its default
+fn from_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// The command-line value when the option was given, else the config value, else the default
+fn merge<T>(matches: &ArgMatches, id: &str, configured: Option<T>, parsed: T) -> T {
+    match configured {
+        Some(value) if !from_command_line(matches, id) => value,
+        _ => parsed,
+    }
+}
 
 #[derive(Parser)]
 pub struct CliArgs {
@@ -37,15 +98,77 @@ pub struct CliArgs {
     #[clap(long = "min-ast-nodes", default_value = "10", value_parser(clap::value_parser!(usize)))]
     pub min_ast_nodes: usize,
 
+    #[clap(long = "clone-type", default_value = "parameterized", value_parser(clap::value_parser!(CloneType)))]
+    pub clone_type: CloneType,
+
+    #[clap(long = "granularity", default_value = "block", value_parser(clap::value_parser!(Granularity)))]
+    pub granularity: Granularity,
+
+    #[clap(long = "similarity", value_parser(parse_similarity))]
+    pub similarity: Option<f64>,
+
+    #[clap(long = "lsh")]
+    pub lsh: bool,
+
+    #[clap(long = "lsh-bands", default_value = "16", value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub lsh_bands: usize,
+
+    #[clap(long = "lsh-rows", default_value = "4", value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub lsh_rows: usize,
+
+    #[clap(long = "engine", default_value = "ast", value_parser(clap::value_parser!(Engine)))]
+    pub engine: Engine,
+
+    #[clap(long = "min-tokens", default_value = "50", value_parser(clap::value_parser!(usize)))]
+    pub min_tokens: usize,
+
+    #[clap(long = "statement-sequences")]
+    pub statement_sequences: bool,
+
+    #[clap(long = "include-snippets")]
+    pub include_snippets: bool,
+
+    #[clap(long = "snippet-max-lines", value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub snippet_max_lines: Option<usize>,
+
     #[clap(short = 'n', long = "threads", default_value = "10", value_parser(clap::value_parser!(usize)))]
     pub threads: usize,
 
+    #[clap(long = "baseline", value_parser(clap::value_parser!(PathBuf)))]
+    pub baseline: Option<PathBuf>,
+
+    #[clap(long = "only-new")]
+    pub only_new: bool,
+
+    #[clap(long = "fail-on-duplicate-lines", value_parser(clap::value_parser!(usize)))]
+    pub fail_on_duplicate_lines: Option<usize>,
+
+    #[clap(long = "fail-on-percentage", value_parser(parse_percentage))]
+    pub fail_on_percentage: Option<f64>,
+
+    #[clap(long = "fail-on-new")]
+    pub fail_on_new: bool,
+
     #[clap(long = "max-file-size", value_parser(clap::value_parser!(u64)), default_value = "1048576")]
     // 1 MB default
     pub max_file_size: u64,
 
+    #[clap(long = "no-ignore")]
+    pub no_ignore: bool,
+
     #[clap(long = "debug")]
     pub debug: bool,
+
+    /// The configuration file in use, given with `--config` or found from the source path
+    #[clap(long = "config", value_parser(clap::value_parser!(PathBuf)))]
+    pub config: Option<PathBuf>,
+
+    #[clap(long = "print-config")]
+    pub print_config: bool,
+
+    /// Directory and language overrides from the configuration file
+    #[clap(skip)]
+    pub overrides: Overrides,
 }
 
 impl CliArgs {
@@ -60,7 +183,7 @@ impl CliArgs {
                     .long("source-path")
                     .value_name("SOURCE")
                     .help("Path to the source code directory")
-                    .required(true)
+                    .required_unless_present("config")
                     .value_parser(clap::value_parser!(PathBuf)),
             )
             .arg(
@@ -86,7 +209,7 @@ impl CliArgs {
                     .short('o')
                     .long("output-format")
                     .value_name("FORMAT")
-                    .help("Output format (e.g., json)")
+                    .help("Output format: json, xml, sarif, html, text, csv, markdown, checkstyle, codeclimate (alias gitlab) or junit")
                     .default_value("json")
                     .value_parser(clap::value_parser!(String)),
             )
@@ -95,7 +218,7 @@ impl CliArgs {
                     .short('f')
                     .long("output-file")
                     .value_name("FILE")
-                    .help("File to write the output to")
+                    .help("File to write the output to, or - for stdout")
                     .default_value("duplications")
                     .value_parser(clap::value_parser!(PathBuf)),
             )
@@ -125,6 +248,119 @@ impl CliArgs {
                     .default_value("10")
                     .value_parser(clap::value_parser!(usize)),
             )
+            .arg(
+                Arg::new("clone-type")
+                    .long("clone-type")
+                    .value_name("CLONE_TYPE")
+                    .help("Leaf text that must match: exact (Type-1), renamed (Type-2) or parameterized")
+                    .default_value("parameterized")
+                    .value_parser(["exact", "renamed", "parameterized"]),
+            )
+            .arg(
+                Arg::new("granularity")
+                    .long("granularity")
+                    .value_name("GRANULARITY")
+                    .help("Compare every large enough block, or only functions and methods")
+                    .default_value("block")
+                    .value_parser(["block", "function"]),
+            )
+            .arg(
+                Arg::new("similarity")
+                    .long("similarity")
+                    .value_name("SIMILARITY")
+                    .help("Also report near-miss (Type-3) clone pairs at or above this similarity, e.g. 0.85")
+                    .value_parser(parse_similarity),
+            )
+            .arg(
+                Arg::new("lsh")
+                    .long("lsh")
+                    .help("Find near-miss candidates with a MinHash/LSH index instead of comparing every pair")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("lsh-bands")
+                    .long("lsh-bands")
+                    .value_name("LSH_BANDS")
+                    .help("Number of LSH bands; more bands find more candidates")
+                    .default_value("16")
+                    .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+            )
+            .arg(
+                Arg::new("lsh-rows")
+                    .long("lsh-rows")
+                    .value_name("LSH_ROWS")
+                    .help("Number of MinHash rows per LSH band; more rows make candidates stricter")
+                    .default_value("4")
+                    .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+            )
+            .arg(
+                Arg::new("engine")
+                    .long("engine")
+                    .value_name("ENGINE")
+                    .help("Detection engine: ast (subtree fingerprints) or token (repeated token runs)")
+                    .default_value("ast")
+                    .value_parser(["ast", "token"]),
+            )
+            .arg(
+                Arg::new("min-tokens")
+                    .long("min-tokens")
+                    .value_name("MIN_TOKENS")
+                    .help("Minimum number of tokens in a clone found by the token engine")
+                    .default_value("50")
+                    .value_parser(clap::value_parser!(usize)),
+            )
+            .arg(
+                Arg::new("statement-sequences")
+                    .long("statement-sequences")
+                    .help("Also report duplicated runs of consecutive statements inside blocks")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("include-snippets")
+                    .long("include-snippets")
+                    .help("Include the byte range, columns and source text of each block in the report")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("snippet-max-lines")
+                    .long("snippet-max-lines")
+                    .value_name("SNIPPET_MAX_LINES")
+                    .help("Truncate each snippet to this many lines")
+                    .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+            )
+            .arg(
+                Arg::new("baseline")
+                    .long("baseline")
+                    .value_name("REPORT")
+                    .help("JSON report of an earlier run; mark each clone class as new, existing or resolved")
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("only-new")
+                    .long("only-new")
+                    .help("Report only the clone classes that are not in the baseline")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("fail-on-duplicate-lines")
+                    .long("fail-on-duplicate-lines")
+                    .value_name("LINES")
+                    .help("Exit with code 1 when more than this many lines are duplicated")
+                    .value_parser(clap::value_parser!(usize)),
+            )
+            .arg(
+                Arg::new("fail-on-percentage")
+                    .long("fail-on-percentage")
+                    .value_name("PERCENT")
+                    .help("Exit with code 1 when more than this percentage of lines is duplicated")
+                    .value_parser(parse_percentage),
+            )
+            .arg(
+                Arg::new("fail-on-new")
+                    .long("fail-on-new")
+                    .help("Exit with code 1 when a clone class is not in the baseline")
+                    .action(clap::ArgAction::SetTrue),
+            )
             .arg(
                 Arg::new("max-file-size")
                     .long("max-file-size")
@@ -133,27 +369,38 @@ impl CliArgs {
                     .default_value("1048576")
                     .value_parser(clap::value_parser!(u64)),
             )
+            .arg(
+                Arg::new("no-ignore")
+                    .long("no-ignore")
+                    .help("Also scan files excluded by .gitignore, git excludes and .resonignore")
+                    .action(clap::ArgAction::SetTrue),
+            )
             .arg(
                 Arg::new("debug")
                     .long("debug")
                     .help("Enable debug mode")
                     .action(clap::ArgAction::SetTrue),
             )
+            .arg(
+                Arg::new("config")
+                    .long("config")
+                    .value_name("CONFIG")
+                    .help("Configuration file; by default the nearest reson.toml from the source path upward")
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("print-config")
+                    .long("print-config")
+                    .help("Print the configuration merged from reson.toml and the command line, then exit")
+                    .action(clap::ArgAction::SetTrue),
+            )
     }
 
-    fn parse_source_path(matches: &clap::ArgMatches) -> PathBuf {
-        let source_path: PathBuf = matches
-            .get_one::<PathBuf>("source-path")
-            .unwrap()
-            .to_path_buf();
-        if let Err(err) = CliArgs::validate_source_path(&source_path) {
-            eprintln!("{}", err);
-            std::process::exit(1);
-        }
-        source_path
+    fn parse_source_path(matches: &clap::ArgMatches) -> Option<PathBuf> {
+        matches.get_one::<PathBuf>("source-path").cloned()
     }
 
-    pub fn validate_source_path(source_path: &PathBuf) -> Result<(), String> {
+    pub fn validate_source_path(source_path: &Path) -> Result<(), String> {
         if !source_path.exists() {
             let error_message = format!(
                 "Error: The source path '{}' does not exist.",
@@ -207,32 +454,417 @@ impl CliArgs {
         *matches.get_one::<usize>("min-ast-nodes").unwrap()
     }
 
+    fn parse_clone_type(matches: &clap::ArgMatches) -> CloneType {
+        matches
+            .get_one::<String>("clone-type")
+            .and_then(|clone_type| clone_type.parse().ok())
+            .unwrap_or_default()
+    }
+
+    fn parse_granularity(matches: &clap::ArgMatches) -> Granularity {
+        matches
+            .get_one::<String>("granularity")
+            .and_then(|granularity| granularity.parse().ok())
+            .unwrap_or_default()
+    }
+
+    fn parse_similarity(matches: &clap::ArgMatches) -> Option<f64> {
+        matches.get_one::<f64>("similarity").copied()
+    }
+
+    fn parse_lsh(matches: &clap::ArgMatches) -> bool {
+        *matches.get_one::<bool>("lsh").unwrap_or(&false)
+    }
+
+    fn parse_lsh_bands(matches: &clap::ArgMatches) -> usize {
+        *matches.get_one::<usize>("lsh-bands").unwrap()
+    }
+
+    fn parse_lsh_rows(matches: &clap::ArgMatches) -> usize {
+        *matches.get_one::<usize>("lsh-rows").unwrap()
+    }
+
+    fn parse_engine(matches: &clap::ArgMatches) -> Engine {
+        matches
+            .get_one::<String>("engine")
+            .and_then(|engine| engine.parse().ok())
+            .unwrap_or_default()
+    }
+
+    fn parse_min_tokens(matches: &clap::ArgMatches) -> usize {
+        *matches.get_one::<usize>("min-tokens").unwrap()
+    }
+
+    fn parse_statement_sequences(matches: &clap::ArgMatches) -> bool {
+        *matches
+            .get_one::<bool>("statement-sequences")
+            .unwrap_or(&false)
+    }
+
+    fn parse_include_snippets(matches: &clap::ArgMatches) -> bool {
+        *matches
+            .get_one::<bool>("include-snippets")
+            .unwrap_or(&false)
+    }
+
+    fn parse_snippet_max_lines(matches: &clap::ArgMatches) -> Option<usize> {
+        matches.get_one::<usize>("snippet-max-lines").copied()
+    }
+
+    fn parse_baseline(matches: &clap::ArgMatches) -> Option<PathBuf> {
+        matches.get_one::<PathBuf>("baseline").cloned()
+    }
+
+    fn parse_only_new(matches: &clap::ArgMatches) -> bool {
+        *matches.get_one::<bool>("only-new").unwrap_or(&false)
+    }
+
+    fn parse_fail_on_duplicate_lines(matches: &clap::ArgMatches) -> Option<usize> {
+        matches.get_one::<usize>("fail-on-duplicate-lines").copied()
+    }
+
+    fn parse_fail_on_percentage(matches: &clap::ArgMatches) -> Option<f64> {
+        matches.get_one::<f64>("fail-on-percentage").copied()
+    }
+
+    fn parse_fail_on_new(matches: &clap::ArgMatches) -> bool {
+        *matches.get_one::<bool>("fail-on-new").unwrap_or(&false)
+    }
+
     fn parse_max_file_size(matches: &clap::ArgMatches) -> u64 {
         *matches.get_one::<u64>("max-file-size").unwrap()
     }
 
+    fn parse_no_ignore(matches: &clap::ArgMatches) -> bool {
+        *matches.get_one::<bool>("no-ignore").unwrap_or(&false)
+    }
+
     fn parse_debug(matches: &clap::ArgMatches) -> bool {
         *matches.get_one::<bool>("debug").unwrap_or(&false)
     }
 
-    fn parse_cli_args(matches: &clap::ArgMatches) -> CliArgs {
-        CliArgs {
-            source_path: CliArgs::parse_source_path(matches),
-            languages: CliArgs::parse_languages(matches),
-            excludes: CliArgs::parse_excludes(matches),
-            output_format: CliArgs::parse_output_format(matches),
-            output_file: CliArgs::parse_output_file(matches),
-            threshold: CliArgs::parse_threshold(matches),
-            min_ast_nodes: CliArgs::parse_min_ast_nodes(matches),
-            threads: CliArgs::parse_threads(matches),
-            max_file_size: CliArgs::parse_max_file_size(matches),
-            debug: CliArgs::parse_debug(matches),
+    fn parse_config(matches: &clap::ArgMatches) -> Option<PathBuf> {
+        matches.get_one::<PathBuf>("config").cloned()
+    }
+
+    fn parse_print_config(matches: &clap::ArgMatches) -> bool {
+        *matches.get_one::<bool>("print-config").unwrap_or(&false)
+    }
+
+    /// Merge the command line over the configuration file, which is given with `--config`
+    /// or found from the source path upward
+    fn parse_cli_args(matches: &clap::ArgMatches) -> Result<CliArgs, String> {
+        let source_path = CliArgs::parse_source_path(matches);
+        let config_path =
+            CliArgs::parse_config(matches).or_else(|| source_path.as_deref().and_then(find_config));
+        let mut config = match &config_path {
+            Some(path) => load_config(path).map_err(|e| format!("Error: {}", e))?,
+            None => Config::default(),
+        };
+
+        // A setting given on the command line applies to every file
+        let mut overrides = std::mem::take(&mut config.overrides);
+        if from_command_line(matches, "threshold") {
+            overrides.clear(|settings| settings.threshold = None);
+        }
+        if from_command_line(matches, "min-ast-nodes") {
+            overrides.clear(|settings| settings.min_ast_nodes = None);
+        }
+        if from_command_line(matches, "excludes") {
+            overrides.clear(|settings| settings.excludes = None);
+        }
+
+        let source_path = source_path.or(config.source_path).ok_or_else(|| {
+            "Error: Pass --source-path or set source_path in the config file.".to_string()
+        })?;
+        let args = CliArgs {
+            source_path,
+            languages: merge(
+                matches,
+                "languages",
+                config.languages,
+                CliArgs::parse_languages(matches),
+            ),
+            excludes: merge(
+                matches,
+                "excludes",
+                config.excludes,
+                CliArgs::parse_excludes(matches),
+            ),
+            output_format: merge(
+                matches,
+                "output-format",
+                config.output_format,
+                CliArgs::parse_output_format(matches),
+            ),
+            output_file: merge(
+                matches,
+                "output-file",
+                config.output_file.map(Some),
+                CliArgs::parse_output_file(matches),
+            ),
+            threshold: merge(
+                matches,
+                "threshold",
+                config.threshold,
+                CliArgs::parse_threshold(matches),
+            ),
+            min_ast_nodes: merge(
+                matches,
+                "min-ast-nodes",
+                config.min_ast_nodes,
+                CliArgs::parse_min_ast_nodes(matches),
+            ),
+            clone_type: merge(
+                matches,
+                "clone-type",
+                config.clone_type,
+                CliArgs::parse_clone_type(matches),
+            ),
+            granularity: merge(
+                matches,
+                "granularity",
+                config.granularity,
+                CliArgs::parse_granularity(matches),
+            ),
+            similarity: merge(
+                matches,
+                "similarity",
+                config.similarity.map(Some),
+                CliArgs::parse_similarity(matches),
+            ),
+            lsh: merge(matches, "lsh", config.lsh, CliArgs::parse_lsh(matches)),
+            lsh_bands: merge(
+                matches,
+                "lsh-bands",
+                config.lsh_bands,
+                CliArgs::parse_lsh_bands(matches),
+            ),
+            lsh_rows: merge(
+                matches,
+                "lsh-rows",
+                config.lsh_rows,
+                CliArgs::parse_lsh_rows(matches),
+            ),
+            engine: merge(
+                matches,
+                "engine",
+                config.engine,
+                CliArgs::parse_engine(matches),
+            ),
+            min_tokens: merge(
+                matches,
+                "min-tokens",
+                config.min_tokens,
+                CliArgs::parse_min_tokens(matches),
+            ),
+            statement_sequences: merge(
+                matches,
+                "statement-sequences",
+                config.statement_sequences,
+                CliArgs::parse_statement_sequences(matches),
+            ),
+            include_snippets: merge(
+                matches,
+                "include-snippets",
+                config.include_snippets,
+                CliArgs::parse_include_snippets(matches),
+            ),
+            snippet_max_lines: merge(
+                matches,
+                "snippet-max-lines",
+                config.snippet_max_lines.map(Some),
+                CliArgs::parse_snippet_max_lines(matches),
+            ),
+            threads: merge(
+                matches,
+                "threads",
+                config.threads,
+                CliArgs::parse_threads(matches),
+            ),
+            baseline: merge(
+                matches,
+                "baseline",
+                config.baseline.map(Some),
+                CliArgs::parse_baseline(matches),
+            ),
+            only_new: merge(
+                matches,
+                "only-new",
+                config.only_new,
+                CliArgs::parse_only_new(matches),
+            ),
+            fail_on_duplicate_lines: merge(
+                matches,
+                "fail-on-duplicate-lines",
+                config.fail_on_duplicate_lines.map(Some),
+                CliArgs::parse_fail_on_duplicate_lines(matches),
+            ),
+            fail_on_percentage: merge(
+                matches,
+                "fail-on-percentage",
+                config.fail_on_percentage.map(Some),
+                CliArgs::parse_fail_on_percentage(matches),
+            ),
+            fail_on_new: merge(
+                matches,
+                "fail-on-new",
+                config.fail_on_new,
+                CliArgs::parse_fail_on_new(matches),
+            ),
+            max_file_size: merge(
+                matches,
+                "max-file-size",
+                config.max_file_size,
+                CliArgs::parse_max_file_size(matches),
+            ),
+            no_ignore: merge(
+                matches,
+                "no-ignore",
+                config.no_ignore,
+                CliArgs::parse_no_ignore(matches),
+            ),
+            debug: merge(
+                matches,
+                "debug",
+                config.debug,
+                CliArgs::parse_debug(matches),
+            ),
+            config: config_path,
+            print_config: CliArgs::parse_print_config(matches),
+            overrides,
+        };
+        args.validate()?;
+        Ok(args)
+    }
+
+    /// Check what clap cannot, since each value may come from the config file instead
+    fn validate(&self) -> Result<(), String> {
+        CliArgs::validate_source_path(&self.source_path)?;
+        if let Some(similarity) = self.similarity {
+            check_similarity(similarity).map_err(|e| format!("Error: {}", e))?;
+        }
+        if let Some(percentage) = self.fail_on_percentage {
+            check_percentage(percentage).map_err(|e| format!("Error: {}", e))?;
+        }
+        let requirements = [
+            (
+                self.lsh && self.similarity.is_none(),
+                "--lsh requires --similarity",
+            ),
+            (
+                self.snippet_max_lines.is_some() && !self.include_snippets,
+                "--snippet-max-lines requires --include-snippets",
+            ),
+            (
+                self.only_new && self.baseline.is_none(),
+                "--only-new requires --baseline",
+            ),
+            (
+                self.fail_on_new && self.baseline.is_none(),
+                "--fail-on-new requires --baseline",
+            ),
+            (
+                self.lsh_bands == 0 || self.lsh_rows == 0 || self.snippet_max_lines == Some(0),
+                "--lsh-bands, --lsh-rows and --snippet-max-lines must be at least 1",
+            ),
+        ];
+        match requirements.iter().find(|(failed, _)| *failed) {
+            Some((_, message)) => Err(format!("Error: {}", message)),
+            None => Ok(()),
         }
     }
 
     pub fn parse() -> Self {
         let matches = Self::command().get_matches();
-        CliArgs::parse_cli_args(&matches)
+        CliArgs::parse_cli_args(&matches).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_ERROR);
+        })
+    }
+
+    /// The merged settings in the layout of `reson.toml`, as shown by `--print-config`
+    pub fn to_config(&self) -> Config {
+        Config {
+            source_path: Some(self.source_path.clone()),
+            languages: Some(self.languages.clone()),
+            excludes: Some(self.excludes.clone()),
+            output_format: Some(self.output_format.clone()),
+            output_file: self.output_file.clone(),
+            threshold: Some(self.threshold),
+            min_ast_nodes: Some(self.min_ast_nodes),
+            clone_type: Some(self.clone_type),
+            granularity: Some(self.granularity),
+            similarity: self.similarity,
+            lsh: Some(self.lsh),
+            lsh_bands: Some(self.lsh_bands),
+            lsh_rows: Some(self.lsh_rows),
+            engine: Some(self.engine),
+            min_tokens: Some(self.min_tokens),
+            statement_sequences: Some(self.statement_sequences),
+            include_snippets: Some(self.include_snippets),
+            snippet_max_lines: self.snippet_max_lines,
+            threads: Some(self.threads),
+            baseline: self.baseline.clone(),
+            only_new: Some(self.only_new),
+            fail_on_duplicate_lines: self.fail_on_duplicate_lines,
+            fail_on_percentage: self.fail_on_percentage,
+            fail_on_new: Some(self.fail_on_new),
+            max_file_size: Some(self.max_file_size),
+            no_ignore: Some(self.no_ignore),
+            debug: Some(self.debug),
+            overrides: self.overrides.clone(),
+        }
+    }
+
+    /// `to_config` as TOML, headed by the file it was merged from
+    pub fn config_toml(&self) -> Result<String, toml::ser::Error> {
+        let origin = match &self.config {
+            Some(path) => format!(
+                "# Merged from {} and the command line; override directories are relative to it\n",
+                path.display()
+            ),
+            None => "# No config file found; defaults and the command line\n".to_string(),
+        };
+        Ok(origin + &toml::to_string(&self.to_config())?)
+    }
+
+    /// The options that affect detection, as recorded in report metadata
+    pub fn report_options(&self) -> ReportOptions {
+        ReportOptions {
+            source_path: self.source_path.to_string_lossy().to_string(),
+            languages: self.languages.clone(),
+            excludes: self.excludes.clone(),
+            threshold: self.threshold,
+            min_ast_nodes: self.min_ast_nodes,
+            clone_type: self.clone_type,
+            granularity: self.granularity,
+            engine: self.engine,
+            min_tokens: self.min_tokens,
+            similarity: self.similarity,
+            lsh: self.lsh,
+            lsh_bands: self.lsh_bands,
+            lsh_rows: self.lsh_rows,
+            statement_sequences: self.statement_sequences,
+            include_snippets: self.include_snippets,
+            snippet_max_lines: self.snippet_max_lines,
+            max_file_size: self.max_file_size,
+            no_ignore: self.no_ignore,
+            baseline: self
+                .baseline
+                .as_ref()
+                .map(|baseline| baseline.to_string_lossy().to_string()),
+            only_new: self.only_new,
+        }
+    }
+
+    /// The limits that decide the exit code
+    pub fn quality_gate(&self) -> QualityGate {
+        QualityGate {
+            max_duplicate_lines: self.fail_on_duplicate_lines,
+            max_percentage: self.fail_on_percentage,
+            fail_on_new: self.fail_on_new,
+        }
     }
 }
 
@@ -410,6 +1042,386 @@ mod tests {
         assert_eq!(min_ast_nodes, 10);
     }
 
+    #[test]
+    fn test_parse_clone_type() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--clone-type",
+                "renamed",
+            ])
+            .unwrap();
+
+        let clone_type = CliArgs::parse_clone_type(&matches);
+        assert_eq!(clone_type, CloneType::Renamed);
+    }
+
+    #[test]
+    fn test_parse_clone_type_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        let clone_type = CliArgs::parse_clone_type(&matches);
+        assert_eq!(clone_type, CloneType::Parameterized);
+    }
+
+    #[test]
+    fn test_parse_clone_type_invalid() {
+        let matches = CliArgs::command().try_get_matches_from(vec![
+            "code-duplication-detector",
+            "--source-path",
+            "src",
+            "--clone-type",
+            "fuzzy",
+        ]);
+
+        assert!(matches.is_err());
+    }
+
+    #[test]
+    fn test_parse_granularity() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--granularity",
+                "function",
+            ])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_granularity(&matches), Granularity::Function);
+    }
+
+    #[test]
+    fn test_parse_granularity_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_granularity(&matches), Granularity::Block);
+    }
+
+    #[test]
+    fn test_parse_similarity() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--similarity",
+                "0.85",
+            ])
+            .unwrap();
+
+        let similarity = CliArgs::parse_similarity(&matches);
+        assert_eq!(similarity, Some(0.85));
+    }
+
+    #[test]
+    fn test_parse_similarity_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_similarity(&matches), None);
+    }
+
+    #[test]
+    fn test_parse_similarity_out_of_range() {
+        for value in ["0", "1.5", "high"] {
+            let matches = CliArgs::command().try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--similarity",
+                value,
+            ]);
+
+            assert!(matches.is_err(), "{} should be rejected", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_lsh() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--similarity",
+                "0.8",
+                "--lsh",
+                "--lsh-bands",
+                "20",
+                "--lsh-rows",
+                "5",
+            ])
+            .unwrap();
+
+        assert!(CliArgs::parse_lsh(&matches));
+        assert_eq!(CliArgs::parse_lsh_bands(&matches), 20);
+        assert_eq!(CliArgs::parse_lsh_rows(&matches), 5);
+    }
+
+    #[test]
+    fn test_parse_lsh_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert!(!CliArgs::parse_lsh(&matches));
+        assert_eq!(CliArgs::parse_lsh_bands(&matches), 16);
+        assert_eq!(CliArgs::parse_lsh_rows(&matches), 4);
+    }
+
+    #[test]
+    fn test_parse_lsh_invalid() {
+        let without_similarity = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--lsh",
+            ])
+            .unwrap();
+        assert!(CliArgs::parse_cli_args(&without_similarity).is_err());
+
+        let zero_rows = CliArgs::command().try_get_matches_from(vec![
+            "code-duplication-detector",
+            "--source-path",
+            "src",
+            "--lsh-rows",
+            "0",
+        ]);
+        assert!(zero_rows.is_err());
+    }
+
+    #[test]
+    fn test_parse_engine() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--engine",
+                "token",
+                "--min-tokens",
+                "80",
+            ])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_engine(&matches), Engine::Token);
+        assert_eq!(CliArgs::parse_min_tokens(&matches), 80);
+    }
+
+    #[test]
+    fn test_parse_engine_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_engine(&matches), Engine::Ast);
+        assert_eq!(CliArgs::parse_min_tokens(&matches), 50);
+    }
+
+    #[test]
+    fn test_parse_engine_invalid() {
+        let matches = CliArgs::command().try_get_matches_from(vec![
+            "code-duplication-detector",
+            "--source-path",
+            "src",
+            "--engine",
+            "regex",
+        ]);
+
+        assert!(matches.is_err());
+    }
+
+    #[test]
+    fn test_parse_statement_sequences() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--statement-sequences",
+            ])
+            .unwrap();
+
+        assert!(CliArgs::parse_statement_sequences(&matches));
+    }
+
+    #[test]
+    fn test_parse_statement_sequences_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert!(!CliArgs::parse_statement_sequences(&matches));
+    }
+
+    #[test]
+    fn test_parse_include_snippets() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--include-snippets",
+                "--snippet-max-lines",
+                "20",
+            ])
+            .unwrap();
+
+        assert!(CliArgs::parse_include_snippets(&matches));
+        assert_eq!(CliArgs::parse_snippet_max_lines(&matches), Some(20));
+    }
+
+    #[test]
+    fn test_parse_include_snippets_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert!(!CliArgs::parse_include_snippets(&matches));
+        assert_eq!(CliArgs::parse_snippet_max_lines(&matches), None);
+    }
+
+    #[test]
+    fn test_parse_snippet_max_lines_requires_snippets() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--snippet-max-lines",
+                "20",
+            ])
+            .unwrap();
+
+        assert!(CliArgs::parse_cli_args(&matches).is_err());
+    }
+
+    #[test]
+    fn test_parse_baseline() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--baseline",
+                "baseline.json",
+                "--only-new",
+            ])
+            .unwrap();
+
+        assert_eq!(
+            CliArgs::parse_baseline(&matches),
+            Some(PathBuf::from("baseline.json"))
+        );
+        assert!(CliArgs::parse_only_new(&matches));
+    }
+
+    #[test]
+    fn test_parse_baseline_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_baseline(&matches), None);
+        assert!(!CliArgs::parse_only_new(&matches));
+    }
+
+    #[test]
+    fn test_parse_only_new_requires_baseline() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--only-new",
+            ])
+            .unwrap();
+
+        assert!(CliArgs::parse_cli_args(&matches).is_err());
+    }
+
+    #[test]
+    fn test_parse_fail_on() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--baseline",
+                "baseline.json",
+                "--fail-on-duplicate-lines",
+                "200",
+                "--fail-on-percentage",
+                "2.5",
+                "--fail-on-new",
+            ])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_fail_on_duplicate_lines(&matches), Some(200));
+        assert_eq!(CliArgs::parse_fail_on_percentage(&matches), Some(2.5));
+        assert!(CliArgs::parse_fail_on_new(&matches));
+
+        let gate = CliArgs::parse_cli_args(&matches).unwrap().quality_gate();
+        assert_eq!(
+            gate,
+            QualityGate {
+                max_duplicate_lines: Some(200),
+                max_percentage: Some(2.5),
+                fail_on_new: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_fail_on_default() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+
+        assert_eq!(CliArgs::parse_fail_on_duplicate_lines(&matches), None);
+        assert_eq!(CliArgs::parse_fail_on_percentage(&matches), None);
+        assert!(!CliArgs::parse_fail_on_new(&matches));
+    }
+
+    #[test]
+    fn test_parse_fail_on_invalid() {
+        for args in [
+            vec!["--fail-on-percentage", "101"],
+            vec!["--fail-on-percentage", "-1"],
+            vec!["--fail-on-duplicate-lines", "many"],
+        ] {
+            let mut argv = vec!["code-duplication-detector", "--source-path", "src"];
+            argv.extend(args);
+            let error = CliArgs::command().try_get_matches_from(argv).unwrap_err();
+            assert_eq!(error.exit_code(), EXIT_ERROR);
+        }
+
+        // New clone classes are only known with a baseline
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--fail-on-new",
+            ])
+            .unwrap();
+        let error = CliArgs::parse_cli_args(&matches).err();
+        assert_eq!(
+            error.as_deref(),
+            Some("Error: --fail-on-new requires --baseline")
+        );
+    }
+
     #[test]
     fn test_parse_max_file_size() {
         let matches = CliArgs::command()
@@ -426,6 +1438,24 @@ mod tests {
         assert_eq!(max_file_size, 1048576);
     }
 
+    #[test]
+    fn test_parse_no_ignore() {
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                "src",
+                "--no-ignore",
+            ])
+            .unwrap();
+        assert!(CliArgs::parse_no_ignore(&matches));
+
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec!["code-duplication-detector", "--source-path", "src"])
+            .unwrap();
+        assert!(!CliArgs::parse_no_ignore(&matches));
+    }
+
     #[test]
     fn test_parse_debug() {
         let matches = CliArgs::command()
@@ -451,6 +1481,103 @@ mod tests {
         assert!(!debug);
     }
 
+    #[test]
+    fn test_parse_cli_args_with_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("reson.toml"),
+            r#"
+threshold = 8
+min_ast_nodes = 14
+excludes = ["*.gen.rs"]
+output_format = "text"
+baseline = "baseline.json"
+
+[overrides.directories.vendor]
+threshold = 30
+min_ast_nodes = 20
+"#,
+        )
+        .unwrap();
+        let source_path = dir.path().join("src");
+
+        // Found from the source path; flags win over the file and its overrides
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                source_path.to_str().unwrap(),
+                "--threshold",
+                "6",
+                "--only-new",
+            ])
+            .unwrap();
+        let cli_args = CliArgs::parse_cli_args(&matches).unwrap();
+
+        let config_path = std::fs::canonicalize(dir.path().join("reson.toml")).unwrap();
+        assert_eq!(cli_args.config, Some(config_path.clone()));
+        assert_eq!(cli_args.source_path, source_path);
+        assert_eq!(cli_args.threshold, 6);
+        assert_eq!(cli_args.min_ast_nodes, 14);
+        assert_eq!(cli_args.excludes, vec!["*.gen.rs"]);
+        assert_eq!(cli_args.output_format, "text");
+        assert_eq!(cli_args.output_file, Some(PathBuf::from("duplications")));
+        assert_eq!(
+            cli_args.baseline,
+            Some(config_path.parent().unwrap().join("baseline.json"))
+        );
+        assert!(cli_args.only_new);
+        let vendor = &cli_args.overrides.directories["vendor"];
+        assert_eq!(vendor.threshold, None);
+        assert_eq!(vendor.min_ast_nodes, Some(20));
+
+        let printed = cli_args.config_toml().unwrap();
+        assert!(printed.starts_with("# Merged from"));
+        assert!(printed.contains("threshold = 6\n"));
+        assert!(printed.contains("[overrides.directories.vendor]\nmin_ast_nodes = 20\n"));
+
+        // Given explicitly, the file can also set the source path
+        std::fs::write(dir.path().join("other.toml"), "source_path = \"src\"\n").unwrap();
+        let config = dir.path().join("other.toml");
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--config",
+                config.to_str().unwrap(),
+            ])
+            .unwrap();
+        let cli_args = CliArgs::parse_cli_args(&matches).unwrap();
+        assert_eq!(cli_args.source_path, source_path);
+        assert_eq!(cli_args.threshold, 5);
+    }
+
+    #[test]
+    fn test_parse_cli_args_with_invalid_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join("reson.toml");
+        std::fs::write(&config, "similarity = 1.5\n").unwrap();
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--source-path",
+                dir.path().to_str().unwrap(),
+            ])
+            .unwrap();
+        assert!(CliArgs::parse_cli_args(&matches).is_err());
+
+        // Without --source-path the config must name the source
+        std::fs::write(&config, "threshold = 3\n").unwrap();
+        let matches = CliArgs::command()
+            .try_get_matches_from(vec![
+                "code-duplication-detector",
+                "--config",
+                config.to_str().unwrap(),
+            ])
+            .unwrap();
+        assert!(CliArgs::parse_cli_args(&matches).is_err());
+    }
+
     #[test]
     fn test_parse_cli_args() {
         let matches = CliArgs::command()
@@ -472,7 +1599,7 @@ mod tests {
             ])
             .unwrap();
 
-        let cli_args = CliArgs::parse_cli_args(&matches);
+        let cli_args = CliArgs::parse_cli_args(&matches).unwrap();
 
         assert_eq!(cli_args.source_path, PathBuf::from("src"));
         assert_eq!(cli_args.excludes, vec!["tests", "temp", "build"]);
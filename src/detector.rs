@@ -1,116 +1,482 @@
-use crate::models::detection_types::{DuplicateBlock, DuplicateReport, ParentFingerprint};
-use crate::parser::ast_parser::parse_file;
+use crate::cli::CliArgs;
+use crate::matching::minhash::{find_candidate_pairs, LshParams};
+use crate::matching::similarity::{
+    find_similar_pairs, score_candidate_pairs, SimilarPair, SimilarityCandidate,
+};
+use crate::matching::suffix_array::find_maximal_repeats;
+use crate::models::code_types::{
+    CloneType, CodeBlock, CodeBlockNode, CodeBlockRef, Engine, ParseOptions, Suppressions, Token,
+};
+use crate::models::detection_types::{
+    BaselineStatus, DuplicateBlock, DuplicateReport, ParentFingerprint,
+};
+use crate::models::report::{
+    AnalyzedFile, BaselineSummary, Metadata, Report, SkipReason, SkippedFile,
+};
+use crate::parser::ast_collection::{collect_suppressions, compute_ast_fingerprint, count_tokens};
+use crate::parser::ast_parser::{parse_file, tokenize_file};
+use crate::utils::baseline::{check_baseline_options, compare_with_baseline, load_baseline};
+use crate::utils::config::{apply_overrides, FileSettings};
 use crate::utils::filters::filter_files;
+use crate::utils::language_mapping::get_language_from_extension;
+use crate::utils::snippets::attach_snippets;
 use dashmap::DashMap;
 use indicatif::ProgressBar;
 use indicatif::ProgressStyle;
 use rayon::prelude::*;
-use rayon::ThreadPoolBuilder;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use reson::POOL_STACK_SIZE;
-use serde_json::Value;
-use std::collections::BTreeSet;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-pub fn detect_duplicates(args: &crate::cli::CliArgs, num_threads: usize) -> Value {
-    let files = filter_files(
-        &args.source_path,
-        &args.languages,
-        &args.excludes,
-        args.max_file_size,
-    );
-    let fingerprints: DashMap<String, Vec<DuplicateBlock>> = DashMap::new();
-    let parent_fingerprints: DashMap<String, ParentFingerprint> = DashMap::new();
+/// A block that passed the per-file filters, ready to be grouped across files
+struct ProcessedBlock {
+    fingerprint: String,
+    parent_fingerprint: Option<ParentFingerprint>,
+    duplicate_block: DuplicateBlock,
+    kind_sequence: Vec<u32>,
+    statements: Vec<CodeBlock>,
+    /// Index of the enclosing block among the processed blocks
+    parent_index: Option<usize>,
+}
 
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} \nProcessing file: {msg}")
-            .unwrap()
-            .progress_chars("#>-"),
-    );
+fn process_blocks(
+    blocks: &[CodeBlockRef],
+    file_path: &str,
+    min_ast_nodes: usize,
+) -> Vec<ProcessedBlock> {
+    let mut indices: HashMap<*const RefCell<CodeBlockNode>, usize> = HashMap::new();
+    let mut processed = Vec::new();
 
-    let pool = ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .stack_size(POOL_STACK_SIZE)
-        .build()
-        .unwrap();
+    for block_rc in blocks {
+        let block = block_rc.borrow();
+        let fingerprint = block.code_block.fingerprint.clone();
+        // Skip blank ASTs and blocks below the configured AST-node floor
+        if fingerprint == "blank_ast" || block.code_block.ast_lines < min_ast_nodes {
+            continue;
+        }
+        let duplicate_block = DuplicateBlock {
+            start_line_number: block.code_block.start_line,
+            end_line_number: block.code_block.end_line,
+            source_file: file_path.to_string(),
+            symbol_name: block.symbol.as_ref().and_then(|symbol| symbol.name.clone()),
+            symbol_kind: block.symbol.as_ref().map(|symbol| symbol.kind.to_string()),
+            context: block.context.clone(),
+            snippet: None,
+            byte_range: Some((block.code_block.start_byte, block.code_block.end_byte)),
+        };
+        let parent_ref = block
+            .parent
+            .as_ref()
+            .and_then(|parent_weak| parent_weak.upgrade());
+        let parent_fingerprint = parent_ref.as_ref().map(|parent_ref| ParentFingerprint {
+            fingerprint: parent_ref.borrow().code_block.fingerprint.clone(),
+        });
+        let parent_index = parent_ref
+            .as_ref()
+            .and_then(|parent_ref| indices.get(&Rc::as_ptr(parent_ref)).copied());
 
-    pool.install(|| {
-        let processed_blocks: Vec<(String, Option<ParentFingerprint>, DuplicateBlock)> = files
-            .par_iter()
-            .filter_map(|file| {
-                pb.set_message(file.to_string_lossy().to_string());
-                if let Ok((blocks, _tree, _source_code)) = parse_file(file, args.threshold) {
-                    let file_path = file.to_string_lossy().to_string();
-                    let result = Some(
-                        blocks
-                            .iter()
-                            .filter_map(|block_rc| {
-                                let block = block_rc.borrow();
-                                let fingerprint = block.code_block.fingerprint.clone();
-                                // Skip blank ASTs and blocks below the configured AST-node floor
-                                if fingerprint == "blank_ast"
-                                    || block.code_block.ast_lines < args.min_ast_nodes
-                                {
-                                    return None;
-                                }
-                                let duplicate_block = DuplicateBlock {
-                                    start_line_number: block.code_block.start_line,
-                                    end_line_number: block.code_block.end_line,
-                                    source_file: file_path.clone(),
-                                };
-                                let parent_fingerprint =
-                                    block.parent.as_ref().and_then(|parent_weak| {
-                                        parent_weak.upgrade().map(|parent_ref| {
-                                            let parent = parent_ref.borrow();
-                                            let parent_fingerprint =
-                                                parent.code_block.fingerprint.clone();
-                                            ParentFingerprint {
-                                                fingerprint: parent_fingerprint,
-                                            }
-                                        })
-                                    });
-                                Some((fingerprint, parent_fingerprint, duplicate_block))
-                            })
-                            .collect::<Vec<_>>(),
-                    );
-                    pb.inc(1);
-                    result
-                } else {
-                    pb.inc(1);
-                    None
+        indices.insert(Rc::as_ptr(block_rc), processed.len());
+        processed.push(ProcessedBlock {
+            fingerprint,
+            parent_fingerprint,
+            duplicate_block,
+            kind_sequence: block.kind_sequence.clone(),
+            statements: block.statements.clone(),
+            parent_index,
+        });
+    }
+
+    processed
+}
+
+/// Totals over the files an engine read, recorded in the report metadata
+#[derive(Default)]
+struct ScanStats {
+    files: usize,
+    lines: usize,
+    tokens: usize,
+    analyzed: Vec<AnalyzedFile>,
+    failures: Vec<SkippedFile>,
+    parsing: Duration,
+    /// `reson-ignore` directives of the files that have any
+    suppressions: HashMap<String, Suppressions>,
+}
+
+impl ScanStats {
+    fn record(&mut self, file: &Path, outcome: Result<(usize, usize), String>) {
+        match outcome {
+            Ok((lines, tokens)) => {
+                self.files += 1;
+                self.lines += lines;
+                self.tokens += tokens;
+                let language = file
+                    .extension()
+                    .and_then(|extension| get_language_from_extension(&extension.to_string_lossy()))
+                    .unwrap_or("unknown");
+                self.analyzed.push(AnalyzedFile {
+                    path: file.to_string_lossy().to_string(),
+                    language: language.to_string(),
+                    lines,
+                });
+            }
+            Err(error) => self.failures.push(SkippedFile {
+                path: file.to_string_lossy().to_string(),
+                reason: SkipReason::ParseFailure,
+                detail: Some(error),
+            }),
+        }
+    }
+
+    fn record_suppressions(&mut self, file: &Path, suppressions: Suppressions) {
+        if !suppressions.is_empty() {
+            self.suppressions
+                .insert(file.to_string_lossy().to_string(), suppressions);
+        }
+    }
+}
+
+fn line_count(block: &DuplicateBlock) -> usize {
+    block.end_line_number - block.start_line_number + 1
+}
+
+fn blocks_overlap(left: &DuplicateBlock, right: &DuplicateBlock) -> bool {
+    left.source_file == right.source_file
+        && left.start_line_number <= right.end_line_number
+        && right.start_line_number <= left.end_line_number
+}
+
+/// Whether two blocks are an exact or near-miss clone of each other
+fn is_clone_pair(
+    blocks: &[ProcessedBlock],
+    similar: &HashSet<(usize, usize)>,
+    left: usize,
+    right: usize,
+) -> bool {
+    left != right
+        && (blocks[left].fingerprint == blocks[right].fingerprint
+            || similar.contains(&(left.min(right), left.max(right))))
+}
+
+/// A near-miss pair is redundant when an enclosing block of either side already forms
+/// a clone with the other side or with its enclosing block
+fn is_covered_by_parents(
+    blocks: &[ProcessedBlock],
+    similar: &HashSet<(usize, usize)>,
+    pair: &SimilarPair,
+) -> bool {
+    let left_parent = blocks[pair.left].parent_index;
+    let right_parent = blocks[pair.right].parent_index;
+    [
+        left_parent.zip(right_parent),
+        left_parent.map(|parent| (parent, pair.right)),
+        right_parent.map(|parent| (pair.left, parent)),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|(left, right)| is_clone_pair(blocks, similar, left, right))
+}
+
+/// Score the pairs of blocks proposed by the MinHash index
+///
+/// Blocks with the same fingerprint share a kind sequence and are never paired with each
+/// other, so only one block per fingerprint goes into the index. A similar pair of these
+/// representatives then stands for every pair of their blocks, so that many copies of
+/// one getter do not fill an LSH bucket with all pairs of its members.
+fn find_lsh_pairs(
+    blocks: &[ProcessedBlock],
+    similarity: f64,
+    params: LshParams,
+) -> Vec<SimilarPair> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<&str, usize> = HashMap::new();
+    for (index, block) in blocks.iter().enumerate() {
+        let group = *group_of
+            .entry(block.fingerprint.as_str())
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[group].push(index);
+    }
+
+    let sequences: Vec<&[u32]> = groups
+        .iter()
+        .map(|group| blocks[group[0]].kind_sequence.as_slice())
+        .collect();
+    let candidates: Vec<SimilarityCandidate> = sequences
+        .iter()
+        .map(|kinds| SimilarityCandidate::new(kinds))
+        .collect();
+    let candidate_pairs = find_candidate_pairs(&sequences, params);
+    let similar_groups =
+        score_candidate_pairs(&candidates, &candidate_pairs, similarity, |_, _| true);
+
+    let mut pairs: Vec<SimilarPair> = similar_groups
+        .iter()
+        .flat_map(|pair| {
+            let right_group = &groups[pair.right];
+            groups[pair.left].iter().flat_map(move |&left| {
+                right_group.iter().map(move |&right| SimilarPair {
+                    left: left.min(right),
+                    right: left.max(right),
+                    similarity: pair.similarity,
+                })
+            })
+        })
+        .filter(|pair| {
+            !blocks_overlap(
+                &blocks[pair.left].duplicate_block,
+                &blocks[pair.right].duplicate_block,
+            )
+        })
+        .collect();
+    pairs.sort_by_key(|pair| (pair.left, pair.right));
+    pairs
+}
+
+/// Report the maximal pairs of blocks whose kind sequences are similar but not identical
+///
+/// Every comparable pair is scored unless LSH parameters are given, in which case only
+/// the pairs proposed by the MinHash index are.
+fn find_near_miss_reports(
+    blocks: &[ProcessedBlock],
+    similarity: f64,
+    clone_type: CloneType,
+    lsh: Option<LshParams>,
+) -> Vec<DuplicateReport> {
+    let pairs = match lsh {
+        Some(params) => find_lsh_pairs(blocks, similarity, params),
+        None => {
+            let candidates: Vec<SimilarityCandidate> = blocks
+                .iter()
+                .map(|block| SimilarityCandidate::new(&block.kind_sequence))
+                .collect();
+            let is_comparable = |left: usize, right: usize| {
+                blocks[left].fingerprint != blocks[right].fingerprint
+                    && !blocks_overlap(
+                        &blocks[left].duplicate_block,
+                        &blocks[right].duplicate_block,
+                    )
+            };
+            find_similar_pairs(&candidates, similarity, is_comparable)
+        }
+    };
+    let similar: HashSet<(usize, usize)> =
+        pairs.iter().map(|pair| (pair.left, pair.right)).collect();
+
+    pairs
+        .iter()
+        .filter(|pair| !is_covered_by_parents(blocks, &similar, pair))
+        .map(|pair| {
+            let (left, right) = (&blocks[pair.left], &blocks[pair.right]);
+            let pair_fingerprints = if left.fingerprint <= right.fingerprint {
+                format!("{}:{}", left.fingerprint, right.fingerprint)
+            } else {
+                format!("{}:{}", right.fingerprint, left.fingerprint)
+            };
+            DuplicateReport {
+                fingerprint: compute_ast_fingerprint(&pair_fingerprints),
+                clone_type,
+                line_count: line_count(&left.duplicate_block)
+                    .max(line_count(&right.duplicate_block)),
+                similarity: Some((pair.similarity * 10000.0).round() / 10000.0),
+                baseline_status: None,
+                blocks: vec![left.duplicate_block.clone(), right.duplicate_block.clone()],
+            }
+        })
+        .collect()
+}
+
+/// Report maximal runs of consecutive statements that repeat across block-like nodes
+fn find_statement_sequence_reports(
+    blocks: &[ProcessedBlock],
+    args: &CliArgs,
+    settings: &FileSettings,
+) -> Vec<DuplicateReport> {
+    // Lay all statement lists out in one text, each closed by a unique separator
+    let mut symbols: HashMap<&str, u32> = HashMap::new();
+    let mut text: Vec<u32> = Vec::new();
+    let mut origins: Vec<(usize, usize)> = Vec::new();
+    let mut separator = u32::MAX;
+    for (block_index, block) in blocks.iter().enumerate() {
+        if block.statements.len() < 2 {
+            continue;
+        }
+        for (statement_index, statement) in block.statements.iter().enumerate() {
+            let next_symbol = symbols.len() as u32;
+            text.push(
+                *symbols
+                    .entry(statement.fingerprint.as_str())
+                    .or_insert(next_symbol),
+            );
+            origins.push((block_index, statement_index));
+        }
+        text.push(separator);
+        separator -= 1;
+        origins.push((block_index, usize::MAX));
+    }
+
+    find_maximal_repeats(&text, 2)
+        .into_iter()
+        .filter_map(|repeat| {
+            let mut occurrences: Vec<(usize, usize)> = Vec::new();
+            for &position in &repeat.positions {
+                let (block_index, start) = origins[position];
+                // A periodic run can overlap itself within one block
+                if let Some(&(last_block, last_start)) = occurrences.last() {
+                    if last_block == block_index && start < last_start + repeat.length {
+                        continue;
+                    }
                 }
+                occurrences.push((block_index, start));
+            }
+
+            // Runs that span whole blocks are already reported as regular clones
+            if occurrences.iter().all(|&(block_index, start)| {
+                start == 0 && repeat.length == blocks[block_index].statements.len()
+            }) {
+                return None;
+            }
+
+            let runs: Vec<&[CodeBlock]> = occurrences
+                .iter()
+                .map(|&(block_index, start)| {
+                    &blocks[block_index].statements[start..start + repeat.length]
+                })
+                .collect();
+            let ast_nodes: usize = runs[0].iter().map(|statement| statement.ast_lines).sum();
+            let first_file = &blocks[occurrences[0].0].duplicate_block.source_file;
+            if ast_nodes < settings.min_ast_nodes(first_file) {
+                return None;
+            }
+
+            let duplicate_blocks: Vec<DuplicateBlock> = occurrences
+                .iter()
+                .zip(&runs)
+                .map(|(&(block_index, _), run)| DuplicateBlock {
+                    start_line_number: run[0].start_line,
+                    end_line_number: run[run.len() - 1].end_line,
+                    source_file: blocks[block_index].duplicate_block.source_file.clone(),
+                    symbol_name: None,
+                    symbol_kind: None,
+                    context: blocks[block_index].duplicate_block.context.clone(),
+                    snippet: None,
+                    byte_range: Some((run[0].start_byte, run[run.len() - 1].end_byte)),
+                })
+                .filter(|block| line_count(block) >= settings.threshold(&block.source_file))
+                .collect();
+            if duplicate_blocks.len() < 2 {
+                return None;
+            }
+
+            let run_fingerprints: Vec<&str> = runs[0]
+                .iter()
+                .map(|statement| statement.fingerprint.as_str())
+                .collect();
+            Some(DuplicateReport {
+                fingerprint: compute_ast_fingerprint(&run_fingerprints.join(":")),
+                clone_type: args.clone_type,
+                line_count: line_count(&duplicate_blocks[0]),
+                similarity: None,
+                baseline_status: None,
+                blocks: duplicate_blocks,
+            })
+        })
+        .collect()
+}
+
+/// Group blocks with equal subtree fingerprints, plus the optional AST-based refinements
+fn find_ast_reports(
+    files: &[PathBuf],
+    args: &CliArgs,
+    settings: &FileSettings,
+    pool: &ThreadPool,
+    pb: &ProgressBar,
+    stats: &mut ScanStats,
+) -> Vec<DuplicateReport> {
+    let fingerprints: DashMap<String, Vec<DuplicateBlock>> = DashMap::new();
+    let parent_fingerprints: DashMap<String, ParentFingerprint> = DashMap::new();
+    let parse_options = ParseOptions {
+        threshold: args.threshold,
+        clone_type: args.clone_type,
+        granularity: args.granularity,
+        kind_sequences: args.similarity.is_some(),
+        statement_sequences: args.statement_sequences,
+    };
+
+    let parsing = Instant::now();
+    let processed_blocks = pool.install(|| {
+        let parsed_files: Vec<_> = files
+            .par_iter()
+            .map(|file| {
+                let file_name = file.to_string_lossy();
+                pb.set_message(file_name.to_string());
+                let parse_options = ParseOptions {
+                    threshold: settings.threshold(&file_name),
+                    ..parse_options
+                };
+                let result = parse_file(file, &parse_options).map(|(blocks, tree, source_code)| {
+                    (
+                        process_blocks(&blocks, &file_name, settings.min_ast_nodes(&file_name)),
+                        source_code.lines().count(),
+                        count_tokens(tree.root_node()),
+                        collect_suppressions(tree.root_node(), &source_code),
+                    )
+                });
+                pb.inc(1);
+                result
             })
-            .flatten()
             .collect();
+        stats.parsing = parsing.elapsed();
+
+        // Parent indices are per file until the blocks are laid out in one list
+        let mut processed_blocks: Vec<ProcessedBlock> = Vec::new();
+        for (file, parsed) in files.iter().zip(parsed_files) {
+            let (mut file_blocks, lines, tokens, suppressions) = match parsed {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    stats.record(file, Err(error));
+                    continue;
+                }
+            };
+            stats.record(file, Ok((lines, tokens)));
+            stats.record_suppressions(file, suppressions);
+            let offset = processed_blocks.len();
+            for block in &mut file_blocks {
+                block.parent_index = block.parent_index.map(|index| index + offset);
+            }
+            processed_blocks.extend(file_blocks);
+        }
 
         // Ensure that writing to fingerprints and parent_fingerprints is synchronized
-        for (fingerprint, parent_fingerprint, duplicate_block) in processed_blocks {
+        for block in &processed_blocks {
             fingerprints
-                .entry(fingerprint.clone())
+                .entry(block.fingerprint.clone())
                 .and_modify(|existing_blocks| {
-                    existing_blocks.push(duplicate_block.clone());
+                    existing_blocks.push(block.duplicate_block.clone());
                 })
-                .or_insert_with(|| vec![duplicate_block.clone()]);
+                .or_insert_with(|| vec![block.duplicate_block.clone()]);
 
-            if let Some(parent) = parent_fingerprint {
+            if let Some(parent) = &block.parent_fingerprint {
                 parent_fingerprints
-                    .entry(fingerprint.clone())
-                    .or_insert(parent);
+                    .entry(block.fingerprint.clone())
+                    .or_insert(parent.clone());
             }
         }
-    });
 
-    pb.finish_with_message(format!(
-        "Processing complete in {:.2} seconds",
-        pb.elapsed().as_secs_f64()
-    ));
+        processed_blocks
+    });
 
     let exceeding_threshold_fingerprints: BTreeSet<String> = fingerprints
         .iter()
         .filter(|entry| {
             entry.value().len() > 1
-                && (entry.value()[0].end_line_number - entry.value()[0].start_line_number + 1)
-                    >= args.threshold
+                && line_count(&entry.value()[0])
+                    >= settings.threshold(&entry.value()[0].source_file)
         })
         .map(|entry| entry.key().clone())
         .collect();
@@ -119,105 +485,540 @@ pub fn detect_duplicates(args: &crate::cli::CliArgs, num_threads: usize) -> Valu
         fingerprints: &DashMap<String, Vec<DuplicateBlock>>,
         parent_fingerprints: &DashMap<String, ParentFingerprint>,
         exceeding_threshold_fingerprints: &BTreeSet<String>,
-        threshold: usize,
-    ) -> (usize, usize, BTreeSet<String>, Vec<DuplicateReport>) {
-        let (duplicate_blocks, duplicate_lines, duplicate_file_set, details): (
-            usize,
-            usize,
-            BTreeSet<String>,
-            Vec<DuplicateReport>,
-        ) = fingerprints
+        settings: &FileSettings,
+        clone_type: CloneType,
+    ) -> Vec<DuplicateReport> {
+        fingerprints
             .iter()
             .filter(|entry| {
                 let blocks = entry.value();
                 blocks.len() > 1
-                    && (blocks[0].end_line_number - blocks[0].start_line_number + 1) >= threshold
-                    && parent_fingerprints.get(entry.key()).map_or(true, |pf| {
+                    && line_count(&blocks[0]) >= settings.threshold(&blocks[0].source_file)
+                    && parent_fingerprints.get(entry.key()).is_none_or(|pf| {
                         !exceeding_threshold_fingerprints.contains(&pf.fingerprint)
                     })
             })
             .map(|entry| {
                 let blocks = entry.value();
-                let line_count: usize = blocks
-                    .iter()
-                    .map(|b| b.end_line_number - b.start_line_number + 1)
-                    .sum();
-                let files: BTreeSet<String> =
-                    blocks.iter().map(|b| b.source_file.clone()).collect();
-                let report = DuplicateReport {
+                DuplicateReport {
                     fingerprint: entry.key().clone(),
+                    clone_type,
                     line_count: blocks[0].end_line_number - blocks[0].start_line_number + 1,
+                    similarity: None,
+                    baseline_status: None,
                     blocks: blocks.clone(),
-                };
-                (blocks.len(), line_count, files, report)
+                }
             })
-            .fold(
-                (0, 0, BTreeSet::new(), Vec::new()),
-                |(acc_blocks, acc_lines, mut acc_files, mut acc_details), (b, l, f, r)| {
-                    acc_files.extend(f);
-                    acc_details.push(r);
-                    (acc_blocks + b, acc_lines + l, acc_files, acc_details)
-                },
-            );
+            .collect()
+    }
 
-        (
-            duplicate_blocks,
-            duplicate_lines,
-            duplicate_file_set,
-            details,
-        )
+    let mut details = filter_and_collect_fingerprints(
+        &fingerprints,
+        &parent_fingerprints,
+        &exceeding_threshold_fingerprints,
+        settings,
+        args.clone_type,
+    );
+
+    if args.statement_sequences {
+        details.extend(find_statement_sequence_reports(
+            &processed_blocks,
+            args,
+            settings,
+        ));
     }
 
-    let (duplicate_blocks, duplicate_lines, duplicate_file_set, details) =
-        filter_and_collect_fingerprints(
-            &fingerprints,
-            &parent_fingerprints,
-            &exceeding_threshold_fingerprints,
-            args.threshold,
-        );
+    if let Some(similarity) = args.similarity {
+        let lsh = args.lsh.then_some(LshParams {
+            bands: args.lsh_bands,
+            rows: args.lsh_rows,
+        });
+        details.extend(pool.install(|| {
+            find_near_miss_reports(&processed_blocks, similarity, args.clone_type, lsh)
+        }));
+    }
+
+    details
+}
+
+/// Report maximal runs of normalized tokens that repeat within or across files
+fn find_token_reports(
+    files: &[PathBuf],
+    args: &CliArgs,
+    settings: &FileSettings,
+    pb: &ProgressBar,
+    stats: &mut ScanStats,
+) -> Vec<DuplicateReport> {
+    let parsing = Instant::now();
+    let parsed_files: Vec<_> = files
+        .par_iter()
+        .map(|file| {
+            pb.set_message(file.to_string_lossy().to_string());
+            let result = tokenize_file(file, args.clone_type).map(|(tokens, tree, source_code)| {
+                let suppressions = collect_suppressions(tree.root_node(), &source_code);
+                (tokens, suppressions, source_code)
+            });
+            pb.inc(1);
+            result
+        })
+        .collect();
+    stats.parsing = parsing.elapsed();
+
+    let mut tokenized_files: Vec<(String, Vec<Token>)> = Vec::new();
+    for (file, parsed) in files.iter().zip(parsed_files) {
+        match parsed {
+            Ok((tokens, suppressions, source_code)) => {
+                stats.record(file, Ok((source_code.lines().count(), tokens.len())));
+                stats.record_suppressions(file, suppressions);
+                tokenized_files.push((file.to_string_lossy().to_string(), tokens));
+            }
+            Err(error) => stats.record(file, Err(error)),
+        }
+    }
+
+    // Lay all files out in one text, each closed by a unique separator
+    let mut symbols: HashMap<u64, u32> = HashMap::new();
+    let mut text: Vec<u32> = Vec::new();
+    let mut origins: Vec<(usize, usize)> = Vec::new();
+    let mut separator = u32::MAX;
+    for (file_index, (_, tokens)) in tokenized_files.iter().enumerate() {
+        for (token_index, token) in tokens.iter().enumerate() {
+            let next_symbol = symbols.len() as u32;
+            text.push(*symbols.entry(token.symbol).or_insert(next_symbol));
+            origins.push((file_index, token_index));
+        }
+        text.push(separator);
+        separator -= 1;
+        origins.push((file_index, usize::MAX));
+    }
+
+    find_maximal_repeats(&text, args.min_tokens.max(1))
+        .into_iter()
+        .filter_map(|repeat| {
+            let mut occurrences: Vec<(usize, usize)> = Vec::new();
+            for &position in &repeat.positions {
+                let (file_index, start) = origins[position];
+                // A periodic run can overlap itself within one file
+                if let Some(&(last_file, last_start)) = occurrences.last() {
+                    if last_file == file_index && start < last_start + repeat.length {
+                        continue;
+                    }
+                }
+                occurrences.push((file_index, start));
+            }
+
+            let blocks: Vec<DuplicateBlock> = occurrences
+                .iter()
+                .map(|&(file_index, start)| {
+                    let (source_file, tokens) = &tokenized_files[file_index];
+                    DuplicateBlock {
+                        start_line_number: tokens[start].start_line,
+                        end_line_number: tokens[start + repeat.length - 1].end_line,
+                        source_file: source_file.clone(),
+                        symbol_name: None,
+                        symbol_kind: None,
+                        context: None,
+                        snippet: None,
+                        byte_range: Some((
+                            tokens[start].start_byte,
+                            tokens[start + repeat.length - 1].end_byte,
+                        )),
+                    }
+                })
+                .filter(|block| line_count(block) >= settings.threshold(&block.source_file))
+                .collect();
+            if blocks.len() < 2 {
+                return None;
+            }
 
-    let summary = serde_json::json!({
-        "duplicateBlocks": duplicate_blocks,
-        "duplicateLines": duplicate_lines,
-        "duplicateFiles": duplicate_file_set.len(),
+            let (file_index, start) = occurrences[0];
+            let mut hasher = blake3::Hasher::new();
+            for token in &tokenized_files[file_index].1[start..start + repeat.length] {
+                hasher.update(&token.symbol.to_le_bytes());
+            }
+            Some(DuplicateReport {
+                fingerprint: hasher.finalize().to_hex().to_string(),
+                clone_type: args.clone_type,
+                line_count: line_count(&blocks[0]),
+                similarity: None,
+                baseline_status: None,
+                blocks,
+            })
+        })
+        .collect()
+}
+
+/// Put the clone classes and their blocks in an order that does not depend on thread
+/// scheduling: longest classes first, then by fingerprint and first block
+fn sort_reports(details: &mut [DuplicateReport]) {
+    for report in details.iter_mut() {
+        report.blocks.sort_by(|left, right| {
+            (
+                &left.source_file,
+                left.start_line_number,
+                left.end_line_number,
+            )
+                .cmp(&(
+                    &right.source_file,
+                    right.start_line_number,
+                    right.end_line_number,
+                ))
+        });
+    }
+    details.sort_by(|left, right| {
+        let first_block = |report: &DuplicateReport| {
+            report
+                .blocks
+                .first()
+                .map(|block| (block.source_file.clone(), block.start_line_number))
+        };
+        right
+            .line_count
+            .cmp(&left.line_count)
+            .then_with(|| left.fingerprint.cmp(&right.fingerprint))
+            .then_with(|| first_block(left).cmp(&first_block(right)))
     });
+}
+
+/// Take the blocks inside `reson-ignore` regions out of their clone classes
+///
+/// A class left with fewer than two blocks is suppressed whole. The suppressed blocks are
+/// returned grouped by clone class, in report order.
+fn apply_suppressions(
+    details: &mut Vec<DuplicateReport>,
+    suppressions: &HashMap<String, Suppressions>,
+) -> Vec<DuplicateReport> {
+    let is_suppressed = |block: &DuplicateBlock| {
+        suppressions
+            .get(&block.source_file)
+            .is_some_and(|file| file.covers(block.start_line_number, block.end_line_number))
+    };
 
-    let result = serde_json::json!({
-        "summary": summary,
-        "records": serde_json::to_value(details).unwrap()
+    let mut suppressed = Vec::new();
+    details.retain_mut(|report| {
+        let hidden = report
+            .blocks
+            .iter()
+            .filter(|block| is_suppressed(block))
+            .count();
+        if hidden == 0 {
+            return true;
+        }
+        if report.blocks.len() - hidden < 2 {
+            suppressed.push(report.clone());
+            return false;
+        }
+        let (hidden, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut report.blocks)
+            .into_iter()
+            .partition(|block| is_suppressed(block));
+        report.blocks = kept;
+        suppressed.push(DuplicateReport {
+            blocks: hidden,
+            ..report.clone()
+        });
+        true
     });
+    suppressed
+}
 
-    Value::Object(result.as_object().unwrap().clone())
+/// Scan the source path and report its clone classes, compared with the baseline report
+/// when one is given
+pub fn detect_duplicates(args: &CliArgs, num_threads: usize) -> Result<Report, std::io::Error> {
+    let started = Instant::now();
+    // Load the baseline first, so that a bad path fails before the scan
+    let baseline = args.baseline.as_deref().map(load_baseline).transpose()?;
+    if let Some(baseline) = &baseline {
+        check_baseline_options(baseline, &args.report_options())?;
+    }
+    let mut selection = filter_files(
+        &args.source_path,
+        &args.languages,
+        &args.excludes,
+        args.max_file_size,
+        !args.no_ignore,
+    );
+    // Directory overrides are relative to the config file that defines them
+    let config_dir = args
+        .config
+        .as_deref()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""));
+    let settings = apply_overrides(
+        &args.overrides,
+        config_dir,
+        &mut selection,
+        FileSettings::new(args.threshold, args.min_ast_nodes),
+    );
+    let files = selection.files;
+    let discovery = started.elapsed();
+
+    let pb = ProgressBar::new(files.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} \nProcessing file: {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .stack_size(POOL_STACK_SIZE)
+        .build()
+        .unwrap();
+
+    let mut stats = ScanStats::default();
+    let searching = Instant::now();
+    let mut details = match args.engine {
+        Engine::Ast => find_ast_reports(&files, args, &settings, &pool, &pb, &mut stats),
+        Engine::Token => {
+            pool.install(|| find_token_reports(&files, args, &settings, &pb, &mut stats))
+        }
+    };
+    let searching = searching.elapsed();
+
+    pb.finish_with_message(format!(
+        "Processing complete in {:.2} seconds",
+        pb.elapsed().as_secs_f64()
+    ));
+
+    sort_reports(&mut details);
+
+    // Suppressed blocks are left out before the baseline, so they never count as new
+    let mut suppressed = apply_suppressions(&mut details, &stats.suppressions);
+
+    let mut resolved = Vec::new();
+    if let Some(baseline) = &baseline {
+        resolved =
+            compare_with_baseline(&mut details, &args.source_path.to_string_lossy(), baseline);
+        if args.only_new {
+            details.retain(|report| report.baseline_status == Some(BaselineStatus::New));
+            resolved.clear();
+        }
+    }
+
+    let snippets = Instant::now();
+    if args.include_snippets {
+        attach_snippets(&mut details, args.snippet_max_lines);
+        attach_snippets(&mut suppressed, args.snippet_max_lines);
+    }
+    let snippets = snippets.elapsed();
+
+    let mut metadata = Metadata::new(args.report_options());
+    metadata.files_scanned = stats.files;
+    metadata.lines_analyzed = stats.lines;
+    metadata.tokens_analyzed = stats.tokens;
+    metadata.skipped_files = selection.skipped;
+    metadata.skipped_files.extend(stats.failures);
+    metadata.timings.discovery = discovery.as_secs_f64();
+    metadata.timings.parsing = stats.parsing.as_secs_f64();
+    metadata.timings.matching = searching.saturating_sub(stats.parsing).as_secs_f64();
+    metadata.timings.snippets = snippets.as_secs_f64();
+    metadata.timings.total = started.elapsed().as_secs_f64();
+
+    let mut report = Report::new(details, &stats.analyzed, metadata);
+    if baseline.is_some() {
+        report.summary.baseline = Some(BaselineSummary::new(&report.records, &resolved));
+    }
+    report.resolved = resolved;
+    report.summary.suppressed_blocks = suppressed.iter().map(|report| report.blocks.len()).sum();
+    report.suppressed = suppressed;
+    Ok(report)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cli::CliArgs;
-    use std::path::{Path, PathBuf};
+    use crate::models::code_types::Granularity;
+    use crate::utils::config::Overrides;
+    use serde_json::Value;
+    use std::path::Path;
 
     fn setup_test_environment() -> PathBuf {
         let test_dir = Path::new("./tests/rust");
         test_dir.to_path_buf()
     }
 
-    #[test]
-    fn test_detect_duplicates_no_duplicates() {
-        let test_dir = setup_test_environment();
-        let args = CliArgs {
-            source_path: test_dir.clone(),
+    fn default_args(source_path: PathBuf) -> CliArgs {
+        CliArgs {
+            source_path,
             languages: vec!["rust".to_string()],
             excludes: vec![],
             output_format: "json".to_string(),
             output_file: None,
-            threshold: 100,
+            threshold: 5,
             min_ast_nodes: 10,
+            clone_type: CloneType::Parameterized,
+            granularity: Granularity::Block,
+            similarity: None,
+            lsh: false,
+            lsh_bands: 16,
+            lsh_rows: 4,
+            engine: Engine::Ast,
+            min_tokens: 50,
+            statement_sequences: false,
+            include_snippets: false,
+            snippet_max_lines: None,
             threads: 1,
+            baseline: None,
+            only_new: false,
+            fail_on_duplicate_lines: None,
+            fail_on_percentage: None,
+            fail_on_new: false,
             max_file_size: 1048576,
+            no_ignore: false,
             debug: false,
+            config: None,
+            print_config: false,
+            overrides: Overrides::default(),
+        }
+    }
+
+    /// The report as the json output format writes it
+    fn detect_json(args: &CliArgs) -> Value {
+        serde_json::to_value(detect_duplicates(args, 1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_detect_duplicates_report_metadata() {
+        let test_dir = setup_test_environment();
+        let args = default_args(test_dir);
+
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert_eq!(report.summary.duplicate_blocks, {
+            report
+                .records
+                .iter()
+                .map(|record| record.blocks.len())
+                .sum::<usize>()
+        });
+        assert_eq!(report.metadata.tool_version, env!("CARGO_PKG_VERSION"));
+        assert!(report.metadata.timestamp.ends_with('Z'));
+        assert_eq!(report.metadata.options, args.report_options());
+        assert_eq!(report.metadata.options.source_path, "./tests/rust");
+        assert_eq!(report.metadata.files_scanned, 3);
+        assert!(report.metadata.lines_analyzed > 0);
+        assert!(report.metadata.tokens_analyzed > report.metadata.lines_analyzed);
+        let timings = &report.metadata.timings;
+        assert!(timings.total >= timings.discovery + timings.parsing);
+    }
+
+    #[test]
+    fn test_detect_duplicates_with_baseline() {
+        let mut baseline = detect_duplicates(&default_args(setup_test_environment()), 1).unwrap();
+        assert!(!baseline.records.is_empty());
+        let mut gone = baseline.records[0].clone();
+        gone.fingerprint = "removed since the baseline".to_string();
+        baseline.records.push(gone);
+        // Shifted lines do not make a class new
+        for block in &mut baseline.records[0].blocks {
+            block.start_line_number += 3;
+            block.end_line_number += 3;
+        }
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), serde_json::to_string(&baseline).unwrap()).unwrap();
+
+        let args = CliArgs {
+            baseline: Some(temp_file.path().to_path_buf()),
+            ..default_args(setup_test_environment())
         };
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert!(report
+            .records
+            .iter()
+            .all(|record| record.baseline_status == Some(BaselineStatus::Existing)));
+        assert_eq!(report.resolved.len(), 1);
+        assert_eq!(report.resolved[0].fingerprint, "removed since the baseline");
+        let counts = report.summary.baseline.as_ref().unwrap();
+        assert_eq!((counts.new, counts.resolved), (0, 1));
+        assert_eq!(counts.existing, report.records.len());
 
-        let result = detect_duplicates(&args, 1);
+        let args = CliArgs {
+            only_new: true,
+            ..args
+        };
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert!(report.records.is_empty());
+        assert!(report.resolved.is_empty());
+        assert_eq!(report.summary.duplicate_blocks, 0);
+        assert!(report.metadata.options.only_new);
+    }
+
+    #[test]
+    fn test_detect_duplicates_missing_baseline() {
+        let args = CliArgs {
+            baseline: Some(PathBuf::from("missing/baseline.json")),
+            ..default_args(setup_test_environment())
+        };
+        let error = detect_duplicates(&args, 1).unwrap_err();
+        assert!(error.to_string().contains("missing/baseline.json"));
+    }
+
+    #[test]
+    fn test_detect_duplicates_duplication_breakdowns() {
+        let args = default_args(setup_test_environment());
+
+        let report = detect_duplicates(&args, 1).unwrap();
+        let summary = &report.summary;
+        assert_eq!(summary.total_lines, report.metadata.lines_analyzed);
+        assert!(summary.unique_duplicate_lines > 0);
+        assert!(summary.unique_duplicate_lines <= summary.duplicate_lines);
+        assert!(summary.duplication_ratio > 0.0 && summary.duplication_ratio <= 1.0);
+        assert_eq!(summary.files.len(), 3);
+        assert_eq!(summary.languages.len(), 1);
+        assert_eq!(summary.languages[0].name, "rust");
+        assert_eq!(summary.languages[0].total_lines, summary.total_lines);
+        let root = summary
+            .directories
+            .iter()
+            .find(|directory| directory.name == "./tests/rust")
+            .unwrap();
+        assert_eq!(root.duplicated_lines, summary.unique_duplicate_lines);
+    }
+
+    #[test]
+    fn test_detect_duplicates_lists_skipped_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_function_sources(temp_dir.path());
+        std::fs::write(temp_dir.path().join("README.md"), "# notes").unwrap();
+        std::fs::write(temp_dir.path().join("large.rs"), "fn f() {}\n".repeat(200)).unwrap();
+        std::fs::write(temp_dir.path().join("binary.rs"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let args = CliArgs {
+            max_file_size: 1024,
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert_eq!(report.metadata.files_scanned, 2);
+
+        let mut skipped: Vec<(String, SkipReason)> = report
+            .metadata
+            .skipped_files
+            .iter()
+            .map(|skipped| {
+                let name = Path::new(&skipped.path).file_name().unwrap();
+                (name.to_string_lossy().to_string(), skipped.reason)
+            })
+            .collect();
+        skipped.sort_by(|left, right| left.0.cmp(&right.0));
+        assert_eq!(
+            skipped,
+            vec![
+                ("README.md".to_string(), SkipReason::UnsupportedExtension),
+                ("binary.rs".to_string(), SkipReason::ParseFailure),
+                ("large.rs".to_string(), SkipReason::TooLarge),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_duplicates_no_duplicates() {
+        let test_dir = setup_test_environment();
+        let args = CliArgs {
+            threshold: 100,
+            ..default_args(test_dir)
+        };
+
+        let result = detect_json(&args);
         assert!(result
             .get("records")
             .unwrap()
@@ -233,20 +1034,9 @@ mod tests {
     #[test]
     fn test_detect_duplicates_with_duplicates() {
         let test_dir = setup_test_environment();
-        let args = CliArgs {
-            source_path: test_dir.clone(),
-            languages: vec!["rust".to_string()],
-            excludes: vec![],
-            output_format: "json".to_string(),
-            output_file: None,
-            threshold: 5,
-            min_ast_nodes: 10,
-            threads: 1,
-            max_file_size: 1048576,
-            debug: false,
-        };
+        let args = default_args(test_dir);
 
-        let result = detect_duplicates(&args, 1);
+        let result = detect_json(&args);
         assert!(!result
             .get("records")
             .unwrap()
@@ -259,27 +1049,46 @@ mod tests {
         assert!(summary["duplicateFiles"].as_u64().unwrap() > 0);
     }
 
+    #[test]
+    fn test_detect_duplicates_orders_records() {
+        let args = CliArgs {
+            similarity: Some(0.8),
+            statement_sequences: true,
+            ..default_args(setup_test_environment())
+        };
+        let report = detect_duplicates(&args, 4).unwrap();
+        assert!(report
+            .records
+            .windows(2)
+            .all(|pair| pair[0].line_count >= pair[1].line_count));
+        assert!(report
+            .records
+            .iter()
+            .all(|record| record.blocks.windows(2).all(|pair| (
+                &pair[0].source_file,
+                pair[0].start_line_number
+            ) <= (
+                &pair[1].source_file,
+                pair[1].start_line_number
+            ))));
+        for _ in 0..3 {
+            assert_eq!(detect_duplicates(&args, 4).unwrap().records, report.records);
+        }
+    }
+
     #[test]
     fn test_detect_duplicates_with_excludes() {
         let test_dir = setup_test_environment();
         let args = CliArgs {
-            source_path: test_dir.clone(),
-            languages: vec!["rust".to_string()],
             excludes: vec![
                 "./tests/rust/testA.rs".to_string(),
                 "./tests/rust/testB.rs".to_string(),
                 "./tests/rust/testC.rs".to_string(),
             ],
-            output_format: "json".to_string(),
-            output_file: None,
-            threshold: 5,
-            min_ast_nodes: 10,
-            threads: 1,
-            max_file_size: 1048576,
-            debug: false,
+            ..default_args(test_dir)
         };
 
-        let result = detect_duplicates(&args, 1);
+        let result = detect_json(&args);
         assert!(result
             .get("records")
             .unwrap()
@@ -292,23 +1101,117 @@ mod tests {
         assert_eq!(summary["duplicateFiles"], 0);
     }
 
+    #[test]
+    fn test_detect_duplicates_with_overrides() {
+        let all = detect_duplicates(&default_args(setup_test_environment()), 1).unwrap();
+        assert!(!all.records.is_empty());
+
+        // Override directories are relative to the config file, here the working directory
+        let overrides: Overrides = toml::from_str(
+            r#"
+[languages.rust]
+threshold = 1000
+"#,
+        )
+        .unwrap();
+        let args = CliArgs {
+            config: Some(PathBuf::from("reson.toml")),
+            overrides,
+            ..default_args(setup_test_environment())
+        };
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert!(report.records.is_empty());
+        assert_eq!(report.metadata.files_scanned, all.metadata.files_scanned);
+
+        let overrides: Overrides = toml::from_str(
+            r#"
+[directories."tests/rust"]
+excludes = ["*testB*"]
+"#,
+        )
+        .unwrap();
+        let args = CliArgs {
+            config: Some(PathBuf::from("reson.toml")),
+            overrides,
+            ..default_args(setup_test_environment())
+        };
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert_eq!(
+            report.metadata.files_scanned,
+            all.metadata.files_scanned - 1
+        );
+        assert!(report
+            .records
+            .iter()
+            .flat_map(|record| &record.blocks)
+            .all(|block| !block.source_file.contains("testB")));
+    }
+
+    #[test]
+    fn test_detect_duplicates_with_suppressions() {
+        let source = std::fs::read_to_string("tests/c/testA.c").unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("a.c"), &source).unwrap();
+        std::fs::write(temp_dir.path().join("b.c"), &source).unwrap();
+        std::fs::write(
+            temp_dir.path().join("generated.c"),
+            format!("/* reson-ignore-file */\n{}", source),
+        )
+        .unwrap();
+        let args = CliArgs {
+            languages: vec!["c".to_string()],
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert!(!report.records.is_empty());
+        assert!(!report.suppressed.is_empty());
+        let in_generated = |record: &DuplicateReport| {
+            record
+                .blocks
+                .iter()
+                .filter(|block| block.source_file.ends_with("generated.c"))
+                .count()
+        };
+        // Every class lost its generated copy, which is listed instead
+        assert!(report
+            .records
+            .iter()
+            .all(|record| in_generated(record) == 0 && record.blocks.len() >= 2));
+        assert!(report
+            .suppressed
+            .iter()
+            .all(|record| in_generated(record) == record.blocks.len()));
+        assert_eq!(
+            report.summary.suppressed_blocks,
+            report
+                .suppressed
+                .iter()
+                .map(|record| record.blocks.len())
+                .sum::<usize>()
+        );
+
+        // With one copy left, the classes are suppressed whole
+        std::fs::remove_file(temp_dir.path().join("b.c")).unwrap();
+        let report = detect_duplicates(&args, 1).unwrap();
+        assert!(report.records.is_empty());
+        assert_eq!(report.summary.duplicate_blocks, 0);
+        assert!(report
+            .suppressed
+            .iter()
+            .all(|record| record.blocks.len() == 2 && in_generated(record) == 1));
+    }
+
     #[test]
     fn test_detect_duplicates_debug_mode() {
         let test_dir = setup_test_environment();
         let args = CliArgs {
-            source_path: test_dir.clone(),
-            languages: vec!["rust".to_string()],
-            excludes: vec![],
-            output_format: "json".to_string(),
-            output_file: None,
             threshold: 1,
-            min_ast_nodes: 10,
-            threads: 1,
-            max_file_size: 1048576,
             debug: true,
+            ..default_args(test_dir)
         };
 
-        let result = detect_duplicates(&args, 1);
+        let result = detect_json(&args);
         assert!(!result
             .get("records")
             .unwrap()
@@ -316,4 +1219,352 @@ mod tests {
             .unwrap()
             .is_empty());
     }
+
+    #[test]
+    fn test_detect_duplicates_records_clone_type() {
+        let test_dir = setup_test_environment();
+        let args = CliArgs {
+            clone_type: CloneType::Exact,
+            ..default_args(test_dir)
+        };
+
+        let result = detect_json(&args);
+        let records = result.get("records").unwrap().as_array().unwrap();
+        assert!(records.iter().all(|record| record["clone_type"] == "exact"));
+    }
+
+    fn write_near_miss_sources(dir: &Path) {
+        let original = r#"
+fn summarize(values: &[i32]) -> i32 {
+    let mut total = 0;
+    let mut largest = i32::MIN;
+    for value in values {
+        total += value;
+        if *value > largest {
+            largest = *value;
+        }
+    }
+    let average = total / values.len() as i32;
+    average + largest
+}
+"#;
+        let edited = r#"
+fn summarize(values: &[i32]) -> i32 {
+    let mut total = 0;
+    let mut largest = i32::MIN;
+    for value in values {
+        total += value;
+        if *value > largest {
+            largest = *value;
+        }
+    }
+    println!("total: {}", total);
+    let average = total / values.len() as i32;
+    average + largest
+}
+"#;
+        std::fs::write(dir.join("original.rs"), original).unwrap();
+        std::fs::write(dir.join("edited.rs"), edited).unwrap();
+    }
+
+    #[test]
+    fn test_detect_duplicates_near_miss() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_near_miss_sources(temp_dir.path());
+
+        let args = default_args(temp_dir.path().to_path_buf());
+        let result = detect_json(&args);
+        let records = result.get("records").unwrap().as_array().unwrap();
+        assert!(records
+            .iter()
+            .all(|record| record.get("similarity").is_none()));
+        assert!(records
+            .iter()
+            .all(|record| record["line_count"].as_u64().unwrap() < 12));
+
+        let args = CliArgs {
+            similarity: Some(0.8),
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let result = detect_json(&args);
+        let records = result.get("records").unwrap().as_array().unwrap();
+        let near_miss: Vec<&Value> = records
+            .iter()
+            .filter(|record| record.get("similarity").is_some())
+            .collect();
+
+        assert_eq!(near_miss.len(), 1);
+        let similarity = near_miss[0]["similarity"].as_f64().unwrap();
+        assert!((0.8..1.0).contains(&similarity));
+        let blocks = near_miss[0]["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_ne!(blocks[0]["source_file"], blocks[1]["source_file"]);
+    }
+
+    #[test]
+    fn test_detect_duplicates_near_miss_with_lsh() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_near_miss_sources(temp_dir.path());
+
+        let exhaustive_args = CliArgs {
+            similarity: Some(0.8),
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let lsh_args = CliArgs {
+            similarity: Some(0.8),
+            lsh: true,
+            lsh_bands: 32,
+            lsh_rows: 2,
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let near_miss = |result: &Value| -> Vec<Value> {
+            result["records"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|record| record.get("similarity").is_some())
+                .cloned()
+                .collect()
+        };
+
+        let exhaustive = near_miss(&detect_json(&exhaustive_args));
+        let lsh = near_miss(&detect_json(&lsh_args));
+        assert_eq!(lsh, exhaustive);
+
+        // Copies of one block go into the index once, but each still pairs up
+        let original = std::fs::read_to_string(temp_dir.path().join("original.rs")).unwrap();
+        for copy in 0..3 {
+            std::fs::write(temp_dir.path().join(format!("copy{}.rs", copy)), &original).unwrap();
+        }
+        let exhaustive = near_miss(&detect_json(&exhaustive_args));
+        let lsh = near_miss(&detect_json(&lsh_args));
+        assert_eq!(exhaustive.len(), 4);
+        assert_eq!(lsh, exhaustive);
+    }
+
+    fn write_statement_sequence_sources(dir: &Path) {
+        let shared_steps = r#"
+    let mut total = 0;
+    let mut count = 0;
+    for value in values {
+        total += value;
+        count += 1;
+    }
+    let average = total / count;
+    println!("average: {}", average);
+"#;
+        let first = format!(
+            "fn first(values: &[i32]) -> i32 {{\n    let offset = 3;{}    offset\n}}\n",
+            shared_steps
+        );
+        let second = format!(
+            "fn second(values: &[i32], scale: i32) {{\n    if scale > 1 {{\n        return;\n    }}{}}}\n",
+            shared_steps
+        );
+        std::fs::write(dir.join("first.rs"), first).unwrap();
+        std::fs::write(dir.join("second.rs"), second).unwrap();
+    }
+
+    #[test]
+    fn test_detect_duplicates_statement_sequences() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_statement_sequence_sources(temp_dir.path());
+
+        let args = default_args(temp_dir.path().to_path_buf());
+        let result = detect_json(&args);
+        assert!(result["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|record| record["line_count"].as_u64().unwrap() < 8));
+
+        let args = CliArgs {
+            statement_sequences: true,
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let result = detect_json(&args);
+        let records = result["records"].as_array().unwrap();
+        let run = records
+            .iter()
+            .find(|record| record["line_count"] == 8)
+            .expect("the shared statements should be reported");
+        let mut lines: Vec<(u64, u64)> = run["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| {
+                (
+                    block["start_line_number"].as_u64().unwrap(),
+                    block["end_line_number"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        lines.sort();
+        assert_eq!(lines, vec![(3, 10), (5, 12)]);
+        let mut contexts: Vec<&str> = run["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| block["context"].as_str().unwrap())
+            .collect();
+        contexts.sort();
+        assert_eq!(contexts, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_detect_duplicates_token_engine() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_statement_sequence_sources(temp_dir.path());
+        // Unbalanced input still tokenizes
+        std::fs::write(
+            temp_dir.path().join("broken.rs"),
+            "fn broken( {\n    let x = ;\n",
+        )
+        .unwrap();
+
+        let args = CliArgs {
+            engine: Engine::Token,
+            min_tokens: 20,
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let result = detect_json(&args);
+        let records = result["records"].as_array().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["clone_type"], "parameterized");
+        assert_eq!(records[0]["line_count"], 8);
+        let mut lines: Vec<(u64, u64)> = records[0]["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| {
+                (
+                    block["start_line_number"].as_u64().unwrap(),
+                    block["end_line_number"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        lines.sort();
+        assert_eq!(lines, vec![(3, 10), (5, 12)]);
+
+        let args = CliArgs {
+            engine: Engine::Token,
+            min_tokens: 200,
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let result = detect_json(&args);
+        assert!(result["records"].as_array().unwrap().is_empty());
+    }
+
+    fn write_function_sources(dir: &Path) {
+        let shared_struct = r#"
+struct Sample {
+    count: usize,
+    total: f64,
+    minimum: f64,
+    maximum: f64,
+}
+"#;
+        let function = |name: &str| {
+            format!(
+                r#"
+fn {}(values: &[f64]) -> f64 {{
+    let mut total = 0.0;
+    for value in values {{
+        total += value;
+    }}
+    total / values.len() as f64
+}}
+"#,
+                name
+            )
+        };
+        std::fs::write(
+            dir.join("first.rs"),
+            format!("{}{}", shared_struct, function("mean")),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("second.rs"),
+            format!("{}{}", shared_struct, function("average")),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_detect_duplicates_function_granularity() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_function_sources(temp_dir.path());
+
+        let args = default_args(temp_dir.path().to_path_buf());
+        let result = detect_json(&args);
+        let records = result["records"].as_array().unwrap();
+        assert!(records
+            .iter()
+            .flat_map(|record| record["blocks"].as_array().unwrap())
+            .any(|block| block.get("symbol_kind").is_none()));
+
+        let args = CliArgs {
+            granularity: Granularity::Function,
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let result = detect_json(&args);
+        let records = result["records"].as_array().unwrap();
+        assert_eq!(records.len(), 1);
+        let mut symbols: Vec<(&str, &str)> = records[0]["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| {
+                (
+                    block["symbol_name"].as_str().unwrap(),
+                    block["symbol_kind"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        symbols.sort();
+        assert_eq!(symbols, vec![("average", "function"), ("mean", "function")]);
+    }
+
+    #[test]
+    fn test_detect_duplicates_include_snippets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_function_sources(temp_dir.path());
+
+        let args = default_args(temp_dir.path().to_path_buf());
+        let result = detect_json(&args);
+        assert!(result["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|record| record["blocks"].as_array().unwrap())
+            .all(|block| block.get("snippet").is_none()));
+
+        let args = CliArgs {
+            granularity: Granularity::Function,
+            include_snippets: true,
+            snippet_max_lines: Some(2),
+            ..default_args(temp_dir.path().to_path_buf())
+        };
+        let result = detect_json(&args);
+        let records = result["records"].as_array().unwrap();
+        for block in records[0]["blocks"].as_array().unwrap() {
+            let snippet = &block["snippet"];
+            let name = block["symbol_name"].as_str().unwrap();
+            assert_eq!(
+                snippet["text"],
+                format!(
+                    "fn {}(values: &[f64]) -> f64 {{\n    let mut total = 0.0;",
+                    name
+                )
+            );
+            assert_eq!(snippet["truncated"], true);
+            assert_eq!(snippet["start_column"], 1);
+            assert_eq!(snippet["end_column"], 2);
+            let source = std::fs::read_to_string(block["source_file"].as_str().unwrap()).unwrap();
+            let start_byte = snippet["start_byte"].as_u64().unwrap() as usize;
+            let end_byte = snippet["end_byte"].as_u64().unwrap() as usize;
+            assert!(source[start_byte..end_byte].starts_with(&format!("fn {}", name)));
+            assert!(source[start_byte..end_byte].ends_with('}'));
+        }
+    }
 }
@@ -1,3 +1,5 @@
+pub mod models;
+
 pub static LARGE_CONTENT_LENGTH_THRESHOLD: usize = 10000;
 pub static LARGE_ARRAY_THRESHOLD: usize = 1000;
 pub static TREE_PARSING_MAX_DEPTH: usize = 1000;
@@ -1,16 +1,25 @@
 mod cli;
 mod detector;
-mod models;
+mod matching;
 mod parser;
 mod utils;
 
-use crate::cli::CliArgs;
+// The report model lives in the library so that other tools can load reports
+use reson::models;
+
+use crate::cli::{CliArgs, EXIT_ERROR, EXIT_GATE_FAILED};
 use crate::detector::detect_duplicates;
 use crate::utils::output::write_output;
 use env_logger::Env;
 use log::LevelFilter;
 
-fn run(args: CliArgs) -> Result<(), Box<dyn std::error::Error>> {
+/// Detect duplicates, write the report and return the quality gate failures
+fn run(args: CliArgs) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if args.print_config {
+        print!("{}", args.config_toml()?);
+        return Ok(Vec::new());
+    }
+
     // Initialize logger
     let log_level = if args.debug {
         LevelFilter::Debug
@@ -20,22 +29,33 @@ fn run(args: CliArgs) -> Result<(), Box<dyn std::error::Error>> {
     env_logger::Builder::from_env(Env::default().default_filter_or(log_level.to_string())).init();
 
     // Execute duplicate detection
-    let duplicates = detect_duplicates(&args, args.threads);
+    let duplicates = detect_duplicates(&args, args.threads)?;
 
     // Output results based on format
     write_output(
         &duplicates,
-        &args.output_format.as_str(),
+        args.output_format.as_str(),
         args.output_file.as_deref(),
     )?;
 
-    Ok(())
+    // The report is written first so that CI can still publish it when the gate fails
+    Ok(args.quality_gate().failures(&duplicates.summary))
 }
 
 fn main() {
     let args = CliArgs::parse();
-    if let Err(e) = run(args) {
-        eprintln!("Application error: {}", e);
+    match run(args) {
+        Ok(failures) if failures.is_empty() => {}
+        Ok(failures) => {
+            for failure in failures {
+                eprintln!("Quality gate failed: {}", failure);
+            }
+            std::process::exit(EXIT_GATE_FAILED);
+        }
+        Err(e) => {
+            eprintln!("Application error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
     }
 }
 
@@ -66,6 +86,6 @@ mod integration_tests {
         ]);
 
         let result = run(args);
-        assert!(result.is_ok());
+        assert!(result.unwrap().is_empty());
     }
 }
@@ -0,0 +1,136 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Number of consecutive node kinds that form one shingle
+const SHINGLE_SIZE: usize = 4;
+
+/// Banding of the MinHash signatures: each signature holds `bands * rows` hashes
+///
+/// Two blocks become candidates when all rows of at least one band agree, so more bands
+/// raise recall and more rows raise precision. The Jaccard similarity at which a pair
+/// has even odds of being proposed is roughly `(1 / bands) ^ (1 / rows)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LshParams {
+    pub bands: usize,
+    pub rows: usize,
+}
+
+/// SplitMix64 finalizer, used as a cheap family of independent hash functions
+fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+/// The distinct hashed k-grams of a kind sequence; short sequences form a single shingle
+pub fn shingles(kinds: &[u32]) -> Vec<u64> {
+    let mut shingles: Vec<u64> = kinds
+        .windows(SHINGLE_SIZE.min(kinds.len()).max(1))
+        .map(|window| {
+            window
+                .iter()
+                .fold(0, |hash, &kind| mix(hash ^ u64::from(kind)))
+        })
+        .collect();
+    shingles.sort_unstable();
+    shingles.dedup();
+    shingles
+}
+
+/// MinHash signature of a shingle set, one minimum per seeded hash function
+pub fn minhash_signature(shingles: &[u64], length: usize) -> Vec<u64> {
+    (0..length as u64)
+        .map(|seed| {
+            let seed = mix(seed);
+            shingles
+                .iter()
+                .map(|&shingle| mix(shingle ^ seed))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+/// Propose the pairs of sequences that share at least one LSH band
+///
+/// Pairs are returned once each, as `(smaller, larger)` indices in ascending order.
+/// Empty sequences have no shingles and are never proposed.
+pub fn find_candidate_pairs(sequences: &[&[u32]], params: LshParams) -> Vec<(usize, usize)> {
+    let signatures: Vec<Option<Vec<u64>>> = sequences
+        .par_iter()
+        .map(|kinds| {
+            (!kinds.is_empty())
+                .then(|| minhash_signature(&shingles(kinds), params.bands * params.rows))
+        })
+        .collect();
+
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (index, signature) in signatures.iter().enumerate() {
+        let Some(signature) = signature else {
+            continue;
+        };
+        for (band, rows) in signature.chunks(params.rows).enumerate() {
+            let key = rows.iter().fold(0, |hash, &row| mix(hash ^ row));
+            buckets.entry((band, key)).or_default().push(index);
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = buckets
+        .values()
+        .filter(|members| members.len() > 1)
+        .flat_map(|members| {
+            members
+                .iter()
+                .enumerate()
+                .flat_map(move |(position, &left)| {
+                    members[position + 1..]
+                        .iter()
+                        .map(move |&right| (left, right))
+                })
+        })
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMS: LshParams = LshParams { bands: 16, rows: 4 };
+
+    #[test]
+    fn test_shingles() {
+        assert_eq!(shingles(&[1, 2, 3, 4, 1, 2, 3, 4, 1]).len(), 4);
+        assert_eq!(shingles(&[1, 2]).len(), 1);
+        assert_ne!(shingles(&[1, 2]), shingles(&[2, 1]));
+        assert!(shingles(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_minhash_signature_is_deterministic() {
+        let set = shingles(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(minhash_signature(&set, 8), minhash_signature(&set, 8));
+        assert_eq!(minhash_signature(&set, 8).len(), 8);
+        assert_eq!(minhash_signature(&[], 2), vec![u64::MAX, u64::MAX]);
+    }
+
+    #[test]
+    fn test_find_candidate_pairs() {
+        let original: Vec<u32> = (0..60).collect();
+        let mut edited = original.clone();
+        edited.insert(30, 99);
+        let unrelated: Vec<u32> = (1000..1060).collect();
+        let sequences: Vec<&[u32]> = vec![&original, &unrelated, &edited, &original, &[]];
+
+        let pairs = find_candidate_pairs(&sequences, PARAMS);
+        assert!(pairs.contains(&(0, 2)));
+        assert!(pairs.contains(&(0, 3)));
+        assert!(pairs.contains(&(2, 3)));
+        assert!(pairs
+            .iter()
+            .all(|&(left, right)| left != 1 && right != 1 && right != 4));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod minhash;
+pub mod similarity;
+pub mod suffix_array;
@@ -0,0 +1,242 @@
+use rayon::prelude::*;
+
+/// A near-miss clone pair: indices into the candidate list and their similarity score
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarPair {
+    pub left: usize,
+    pub right: usize,
+    pub similarity: f64,
+}
+
+/// Kind sequence of one candidate block, with a sorted copy for the multiset bound
+pub struct SimilarityCandidate<'a> {
+    pub kinds: &'a [u32],
+    sorted_kinds: Vec<u32>,
+}
+
+impl<'a> SimilarityCandidate<'a> {
+    pub fn new(kinds: &'a [u32]) -> Self {
+        let mut sorted_kinds = kinds.to_vec();
+        sorted_kinds.sort_unstable();
+        SimilarityCandidate {
+            kinds,
+            sorted_kinds,
+        }
+    }
+}
+
+/// Dice coefficient of two lengths, the best score their sequences could reach
+fn length_bound(left: usize, right: usize) -> f64 {
+    if left + right == 0 {
+        return 1.0;
+    }
+    2.0 * left.min(right) as f64 / (left + right) as f64
+}
+
+/// Dice coefficient over the multisets of kinds, an upper bound of `sequence_similarity`
+fn multiset_similarity(left: &[u32], right: &[u32]) -> f64 {
+    if left.is_empty() && right.is_empty() {
+        return 1.0;
+    }
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    2.0 * common as f64 / (left.len() + right.len()) as f64
+}
+
+/// Dice coefficient over the longest common subsequence of two kind sequences
+pub fn sequence_similarity(left: &[u32], right: &[u32]) -> f64 {
+    if left.is_empty() && right.is_empty() {
+        return 1.0;
+    }
+    let mut previous = vec![0usize; right.len() + 1];
+    let mut current = vec![0usize; right.len() + 1];
+    for left_kind in left {
+        for (j, right_kind) in right.iter().enumerate() {
+            current[j + 1] = if left_kind == right_kind {
+                previous[j] + 1
+            } else {
+                previous[j + 1].max(current[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    2.0 * previous[right.len()] as f64 / (left.len() + right.len()) as f64
+}
+
+/// Score a candidate pair, skipping the quadratic comparison when a cheap bound rules it out
+pub fn score_pair(
+    left: &SimilarityCandidate,
+    right: &SimilarityCandidate,
+    threshold: f64,
+) -> Option<f64> {
+    if length_bound(left.kinds.len(), right.kinds.len()) < threshold
+        || multiset_similarity(&left.sorted_kinds, &right.sorted_kinds) < threshold
+    {
+        return None;
+    }
+    let similarity = sequence_similarity(left.kinds, right.kinds);
+    (similarity >= threshold).then_some(similarity)
+}
+
+/// Compare every comparable pair of candidates and keep those at or above the threshold
+///
+/// Candidates are visited in order of length so that each one is only compared with the
+/// others whose length still allows the threshold to be reached.
+pub fn find_similar_pairs<F>(
+    candidates: &[SimilarityCandidate],
+    threshold: f64,
+    is_comparable: F,
+) -> Vec<SimilarPair>
+where
+    F: Fn(usize, usize) -> bool + Sync,
+{
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by_key(|&index| candidates[index].kinds.len());
+
+    let mut pairs: Vec<SimilarPair> = order
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(position, &left)| {
+            let left_len = candidates[left].kinds.len();
+            order[position + 1..]
+                .iter()
+                .take_while(move |&&right| {
+                    length_bound(left_len, candidates[right].kinds.len()) >= threshold
+                })
+                .filter(|&&right| is_comparable(left, right))
+                .filter_map(|&right| {
+                    score_pair(&candidates[left], &candidates[right], threshold).map(|similarity| {
+                        SimilarPair {
+                            left: left.min(right),
+                            right: left.max(right),
+                            similarity,
+                        }
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    pairs.sort_by_key(|pair| (pair.left, pair.right));
+    pairs
+}
+
+/// Score explicit candidate pairs, such as those proposed by an LSH index
+pub fn score_candidate_pairs<F>(
+    candidates: &[SimilarityCandidate],
+    pairs: &[(usize, usize)],
+    threshold: f64,
+    is_comparable: F,
+) -> Vec<SimilarPair>
+where
+    F: Fn(usize, usize) -> bool + Sync,
+{
+    let mut pairs: Vec<SimilarPair> = pairs
+        .par_iter()
+        .filter(|&&(left, right)| left != right && is_comparable(left, right))
+        .filter_map(|&(left, right)| {
+            score_pair(&candidates[left], &candidates[right], threshold).map(|similarity| {
+                SimilarPair {
+                    left: left.min(right),
+                    right: left.max(right),
+                    similarity,
+                }
+            })
+        })
+        .collect();
+
+    pairs.sort_by_key(|pair| (pair.left, pair.right));
+    pairs.dedup_by_key(|pair| (pair.left, pair.right));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_similarity() {
+        assert_eq!(sequence_similarity(&[1, 2, 3], &[1, 2, 3]), 1.0);
+        assert_eq!(sequence_similarity(&[1, 2, 3], &[4, 5, 6]), 0.0);
+        // One inserted node: LCS of 4 over 4 + 5 nodes
+        let similarity = sequence_similarity(&[1, 2, 3, 4], &[1, 2, 9, 3, 4]);
+        assert!((similarity - 8.0 / 9.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_multiset_similarity_bounds_sequence_similarity() {
+        let left = [3, 1, 2, 1];
+        let right = [1, 2, 1, 3];
+        let left_candidate = SimilarityCandidate::new(&left);
+        let right_candidate = SimilarityCandidate::new(&right);
+
+        let bound =
+            multiset_similarity(&left_candidate.sorted_kinds, &right_candidate.sorted_kinds);
+        assert_eq!(bound, 1.0);
+        assert!(sequence_similarity(&left, &right) <= bound);
+    }
+
+    #[test]
+    fn test_score_pair_respects_threshold() {
+        let original = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let edited = [1, 2, 3, 4, 11, 5, 6, 7, 8, 9, 10];
+        let original = SimilarityCandidate::new(&original);
+        let edited = SimilarityCandidate::new(&edited);
+
+        assert!(score_pair(&original, &edited, 0.9).is_some());
+        assert!(score_pair(&original, &edited, 0.99).is_none());
+    }
+
+    #[test]
+    fn test_find_similar_pairs() {
+        let sequences: Vec<Vec<u32>> = vec![
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            vec![20, 21, 22, 23],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+        ];
+        let candidates: Vec<SimilarityCandidate> = sequences
+            .iter()
+            .map(|kinds| SimilarityCandidate::new(kinds))
+            .collect();
+
+        let pairs = find_similar_pairs(&candidates, 0.9, |left, right| {
+            // Pretend the identical pair was already reported as an exact clone
+            !(left.min(right) == 0 && left.max(right) == 3)
+        });
+
+        let indices: Vec<(usize, usize)> =
+            pairs.iter().map(|pair| (pair.left, pair.right)).collect();
+        assert_eq!(indices, vec![(0, 2), (2, 3)]);
+        assert!(pairs.iter().all(|pair| pair.similarity >= 0.9));
+    }
+
+    #[test]
+    fn test_score_candidate_pairs() {
+        let sequences: Vec<Vec<u32>> = vec![
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            vec![20, 21, 22, 23],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        ];
+        let candidates: Vec<SimilarityCandidate> = sequences
+            .iter()
+            .map(|kinds| SimilarityCandidate::new(kinds))
+            .collect();
+
+        let pairs = score_candidate_pairs(&candidates, &[(2, 0), (0, 1), (0, 2)], 0.9, |_, _| true);
+
+        let indices: Vec<(usize, usize)> =
+            pairs.iter().map(|pair| (pair.left, pair.right)).collect();
+        assert_eq!(indices, vec![(0, 2)]);
+    }
+}
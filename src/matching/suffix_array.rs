@@ -0,0 +1,196 @@
+/// A substring that occurs at several positions and cannot be extended in either direction
+/// without losing one of its occurrences
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repeat {
+    pub length: usize,
+    pub positions: Vec<usize>,
+}
+
+/// Build the suffix array of `text` by prefix doubling
+pub fn build_suffix_array(text: &[u32]) -> Vec<usize> {
+    let n = text.len();
+    let mut suffix_array: Vec<usize> = (0..n).collect();
+    if n < 2 {
+        return suffix_array;
+    }
+
+    let mut rank: Vec<usize> = text.iter().map(|&symbol| symbol as usize).collect();
+    let mut keys: Vec<(usize, usize)> = vec![(0, 0); n];
+    let mut next_rank = vec![0; n];
+    let mut step = 1;
+    loop {
+        for (index, key) in keys.iter_mut().enumerate() {
+            // Suffixes that end before the second half sort first
+            let second = if index + step < n {
+                rank[index + step] + 1
+            } else {
+                0
+            };
+            *key = (rank[index], second);
+        }
+        suffix_array.sort_unstable_by_key(|&index| keys[index]);
+
+        next_rank[suffix_array[0]] = 0;
+        for position in 1..n {
+            let (previous, current) = (suffix_array[position - 1], suffix_array[position]);
+            next_rank[current] = next_rank[previous] + usize::from(keys[previous] != keys[current]);
+        }
+        std::mem::swap(&mut rank, &mut next_rank);
+
+        if rank[suffix_array[n - 1]] == n - 1 || step >= n {
+            break;
+        }
+        step *= 2;
+    }
+
+    suffix_array
+}
+
+/// Longest common prefix of each suffix with its predecessor in the suffix array (Kasai)
+pub fn build_lcp_array(text: &[u32], suffix_array: &[usize]) -> Vec<usize> {
+    let n = text.len();
+    let mut rank = vec![0; n];
+    for (position, &suffix) in suffix_array.iter().enumerate() {
+        rank[suffix] = position;
+    }
+
+    let mut lcp = vec![0; n];
+    let mut common = 0;
+    for suffix in 0..n {
+        if rank[suffix] == 0 {
+            common = 0;
+            continue;
+        }
+        let previous = suffix_array[rank[suffix] - 1];
+        while suffix + common < n
+            && previous + common < n
+            && text[suffix + common] == text[previous + common]
+        {
+            common += 1;
+        }
+        lcp[rank[suffix]] = common;
+        common = common.saturating_sub(1);
+    }
+
+    lcp
+}
+
+/// A repeat is left-maximal unless every occurrence is preceded by the same symbol
+fn is_left_maximal(text: &[u32], positions: &[usize]) -> bool {
+    let preceding = |position: usize| position.checked_sub(1).map(|index| text[index]);
+    let first = preceding(positions[0]);
+    first.is_none()
+        || positions[1..]
+            .iter()
+            .any(|&position| preceding(position) != first)
+}
+
+/// Find every maximal repeat of at least `min_length` symbols
+///
+/// Symbols that must never be part of a repeat, such as separators between sequences,
+/// should each be unique within `text`.
+pub fn find_maximal_repeats(text: &[u32], min_length: usize) -> Vec<Repeat> {
+    let suffix_array = build_suffix_array(text);
+    let lcp = build_lcp_array(text, &suffix_array);
+    let mut repeats = Vec::new();
+
+    // Walk the LCP intervals bottom-up; each popped interval is right-maximal
+    let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+    for position in 1..=text.len() {
+        let current_lcp = if position < text.len() {
+            lcp[position]
+        } else {
+            0
+        };
+        let mut left_bound = position - 1;
+        while current_lcp < stack.last().map_or(0, |&(length, _)| length) {
+            let (length, interval_start) = stack.pop().unwrap();
+            if length >= min_length {
+                let mut positions: Vec<usize> = suffix_array[interval_start..position].to_vec();
+                positions.sort_unstable();
+                if is_left_maximal(text, &positions) {
+                    repeats.push(Repeat { length, positions });
+                }
+            }
+            left_bound = interval_start;
+        }
+        if current_lcp > stack.last().map_or(0, |&(length, _)| length) {
+            stack.push((current_lcp, left_bound));
+        }
+    }
+
+    repeats.sort_by(|left, right| {
+        right
+            .length
+            .cmp(&left.length)
+            .then_with(|| left.positions.cmp(&right.positions))
+    });
+    repeats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive_suffix_array(text: &[u32]) -> Vec<usize> {
+        let mut suffixes: Vec<usize> = (0..text.len()).collect();
+        suffixes.sort_by(|&left, &right| text[left..].cmp(&text[right..]));
+        suffixes
+    }
+
+    #[test]
+    fn test_build_suffix_array() {
+        // "banana"
+        let text = [2, 1, 3, 1, 3, 1];
+        assert_eq!(build_suffix_array(&text), vec![5, 3, 1, 0, 4, 2]);
+
+        let text = [1, 1, 1, 1, 2, 1, 1, 2, 0, 3];
+        assert_eq!(build_suffix_array(&text), naive_suffix_array(&text));
+    }
+
+    #[test]
+    fn test_build_lcp_array() {
+        let text = [2, 1, 3, 1, 3, 1];
+        let suffix_array = build_suffix_array(&text);
+        assert_eq!(
+            build_lcp_array(&text, &suffix_array),
+            vec![0, 1, 3, 0, 0, 2]
+        );
+    }
+
+    #[test]
+    fn test_find_maximal_repeats() {
+        // a b c d | x a b c d y | a b c z, with unique separators 100 and 101
+        let text = [1, 2, 3, 4, 100, 9, 1, 2, 3, 4, 8, 101, 1, 2, 3, 7];
+        let repeats = find_maximal_repeats(&text, 3);
+
+        assert_eq!(
+            repeats,
+            vec![
+                Repeat {
+                    length: 4,
+                    positions: vec![0, 6],
+                },
+                Repeat {
+                    length: 3,
+                    positions: vec![0, 6, 12],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_maximal_repeats_skips_non_left_maximal() {
+        // "x a b c" twice: "a b c" alone is always preceded by x
+        let text = [9, 1, 2, 3, 100, 9, 1, 2, 3];
+        let repeats = find_maximal_repeats(&text, 3);
+
+        assert_eq!(
+            repeats,
+            vec![Repeat {
+                length: 4,
+                positions: vec![0, 5],
+            }]
+        );
+    }
+}
@@ -1,6 +1,156 @@
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::rc::{Rc, Weak};
+use std::str::FromStr;
+
+/// Which leaf text takes part in a fingerprint, from strictest to most tolerant
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CloneType {
+    /// Type-1: identifiers, literals and tokens must match exactly
+    Exact,
+    /// Type-2: identifiers are normalized, literals and tokens must match
+    Renamed,
+    /// Identifiers and literals are both normalized, only the structure must match
+    #[default]
+    Parameterized,
+}
+
+impl CloneType {
+    /// The name used by `--clone-type` and in reports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CloneType::Exact => "exact",
+            CloneType::Renamed => "renamed",
+            CloneType::Parameterized => "parameterized",
+        }
+    }
+
+    pub fn keeps_identifiers(&self) -> bool {
+        matches!(self, CloneType::Exact)
+    }
+
+    pub fn keeps_literals(&self) -> bool {
+        matches!(self, CloneType::Exact | CloneType::Renamed)
+    }
+
+    pub fn keeps_tokens(&self) -> bool {
+        matches!(self, CloneType::Exact | CloneType::Renamed)
+    }
+}
+
+impl FromStr for CloneType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "exact" => Ok(CloneType::Exact),
+            "renamed" => Ok(CloneType::Renamed),
+            "parameterized" => Ok(CloneType::Parameterized),
+            _ => Err(format!("Unsupported clone type: {}", value)),
+        }
+    }
+}
+
+/// How source files are turned into comparable units
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    /// Fingerprint AST subtrees
+    #[default]
+    Ast,
+    /// Find repeated runs in the stream of normalized leaf tokens
+    Token,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "ast" => Ok(Engine::Ast),
+            "token" => Ok(Engine::Token),
+            _ => Err(format!("Unsupported engine: {}", value)),
+        }
+    }
+}
+
+/// Which syntax nodes are compared as clone candidates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// Every named node that spans enough lines
+    #[default]
+    Block,
+    /// Only function-like nodes: functions, methods, constructors and closures
+    Function,
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "block" => Ok(Granularity::Block),
+            "function" => Ok(Granularity::Function),
+            _ => Err(format!("Unsupported granularity: {}", value)),
+        }
+    }
+}
+
+/// Name and normalized kind of a function-like node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Absent for anonymous functions that are not bound to a name
+    pub name: Option<String>,
+    /// One of `function`, `method`, `constructor` or `closure`
+    pub kind: &'static str,
+}
+
+/// A leaf token of the syntax tree, with the text the clone type ignores normalized away
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub symbol: u64,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Lines of a file left out of the report by `reson-ignore` comments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Suppressions {
+    /// Set by `reson-ignore-file`
+    pub file: bool,
+    /// Inclusive, 1-based line ranges
+    pub ranges: Vec<(usize, usize)>,
+}
+
+impl Suppressions {
+    pub fn is_empty(&self) -> bool {
+        !self.file && self.ranges.is_empty()
+    }
+
+    /// Whether the lines from `start` to `end` lie in a suppressed range
+    pub fn covers(&self, start: usize, end: usize) -> bool {
+        self.file
+            || self
+                .ranges
+                .iter()
+                .any(|&(first, last)| first <= start && end <= last)
+    }
+}
+
+/// Options that control which blocks `parse_file` extracts and what it records for them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    pub threshold: usize,
+    pub clone_type: CloneType,
+    pub granularity: Granularity,
+    pub kind_sequences: bool,
+    pub statement_sequences: bool,
+}
 
 #[derive(Debug, Clone, Eq)]
 pub struct CodeBlock {
@@ -18,6 +168,14 @@ pub struct CodeBlock {
 pub struct CodeBlockNode {
     pub code_block: CodeBlock,
     pub parent: Option<Weak<RefCell<CodeBlockNode>>>,
+    /// Preorder node kinds of the block, only recorded for near-miss matching
+    pub kind_sequence: Vec<u32>,
+    /// Statements of a block-like node, only recorded for statement-sequence matching
+    pub statements: Vec<CodeBlock>,
+    /// Set when the block is a function-like node
+    pub symbol: Option<Symbol>,
+    /// Qualified path of the named scopes enclosing the block, itself included
+    pub context: Option<String>,
 }
 
 pub type CodeBlockRef = Rc<RefCell<CodeBlockNode>>;
@@ -55,6 +213,61 @@ mod tests {
     use super::*;
     use std::collections::BTreeSet;
 
+    #[test]
+    fn test_clone_type_from_str() {
+        assert_eq!("exact".parse::<CloneType>(), Ok(CloneType::Exact));
+        assert_eq!("renamed".parse::<CloneType>(), Ok(CloneType::Renamed));
+        assert_eq!(
+            "parameterized".parse::<CloneType>(),
+            Ok(CloneType::Parameterized)
+        );
+        assert!("fuzzy".parse::<CloneType>().is_err());
+        for clone_type in [
+            CloneType::Exact,
+            CloneType::Renamed,
+            CloneType::Parameterized,
+        ] {
+            assert_eq!(clone_type.as_str().parse::<CloneType>(), Ok(clone_type));
+        }
+        assert_eq!(CloneType::default(), CloneType::Parameterized);
+    }
+
+    #[test]
+    fn test_engine_from_str() {
+        assert_eq!("ast".parse::<Engine>(), Ok(Engine::Ast));
+        assert_eq!("token".parse::<Engine>(), Ok(Engine::Token));
+        assert!("regex".parse::<Engine>().is_err());
+        assert_eq!(Engine::default(), Engine::Ast);
+    }
+
+    #[test]
+    fn test_suppressions_covers() {
+        let suppressions = Suppressions {
+            file: false,
+            ranges: vec![(10, 20)],
+        };
+        assert!(suppressions.covers(10, 20));
+        assert!(suppressions.covers(12, 15));
+        assert!(!suppressions.covers(8, 12));
+        assert!(!suppressions.covers(21, 30));
+        assert!(!suppressions.is_empty());
+
+        let file = Suppressions {
+            file: true,
+            ranges: Vec::new(),
+        };
+        assert!(file.covers(1, 1000));
+        assert!(Suppressions::default().is_empty());
+    }
+
+    #[test]
+    fn test_granularity_from_str() {
+        assert_eq!("block".parse::<Granularity>(), Ok(Granularity::Block));
+        assert_eq!("function".parse::<Granularity>(), Ok(Granularity::Function));
+        assert!("file".parse::<Granularity>().is_err());
+        assert_eq!(Granularity::default(), Granularity::Block);
+    }
+
     #[test]
     fn test_codeblock_ordering() {
         let block1 = CodeBlock {
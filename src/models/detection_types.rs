@@ -1,24 +1,70 @@
-use serde::Serialize;
+use crate::models::code_types::CloneType;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Debug, Clone)]
+/// Source text of a block with its exact position, included on request
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snippet {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// 1-based character column of the first character
+    pub start_column: usize,
+    /// 1-based character column just past the last character
+    pub end_column: usize,
+    pub text: String,
+    /// Whether `text` was cut short of `end_byte`
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DuplicateBlock {
     pub start_line_number: usize,
     pub end_line_number: usize,
     pub source_file: String,
+    /// Name of the function-like node the block covers, when it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol_name: Option<String>,
+    /// Kind of the function-like node the block covers: function, method, constructor or closure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol_kind: Option<String>,
+    /// Qualified path of the enclosing class, impl, module or function, e.g. `UserService::save`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+    /// Byte range of the block in its file, used to build the snippet
+    #[serde(skip)]
+    pub byte_range: Option<(usize, usize)>,
+}
+
+/// How a clone class compares with the classes of a `--baseline` report
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BaselineStatus {
+    /// Not in the baseline
+    New,
+    /// Also in the baseline, possibly at other lines
+    Existing,
+    /// In the baseline but no longer found
+    Resolved,
 }
 
-#[derive(Serialize)]
+/// A clone class; fields added after the first release default when missing, so that
+/// older reports still load as baselines
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DuplicateReport {
     pub fingerprint: String,
+    #[serde(default)]
+    pub clone_type: CloneType,
     pub line_count: usize,
+    /// Similarity score of a near-miss pair, absent for exact clone classes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
+    /// Set when the run was compared with a baseline report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_status: Option<BaselineStatus>,
     pub blocks: Vec<DuplicateBlock>,
 }
 
-#[derive(Serialize)]
-pub struct DuplicateReportXML<T> {
-    pub items: T,
-}
-
 #[derive(Serialize, Clone)]
 pub struct ParentFingerprint {
     pub fingerprint: String,
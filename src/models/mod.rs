@@ -1,2 +1,3 @@
 pub mod code_types;
 pub mod detection_types;
+pub mod report;
@@ -0,0 +1,641 @@
+use crate::models::code_types::{CloneType, Engine, Granularity};
+use crate::models::detection_types::{BaselineStatus, DuplicateReport};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A file that was parsed and compared, the input to the duplication breakdowns
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyzedFile {
+    pub path: String,
+    pub language: String,
+    pub lines: usize,
+}
+
+/// Duplication of one file, directory or language
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Breakdown {
+    /// File or directory path, or language name
+    pub name: String,
+    pub total_lines: usize,
+    /// Lines covered by at least one duplicate block
+    pub duplicated_lines: usize,
+    pub duplication_ratio: f64,
+}
+
+impl Breakdown {
+    fn new(name: &str, total_lines: usize, duplicated_lines: usize) -> Self {
+        Breakdown {
+            name: name.to_string(),
+            total_lines,
+            duplicated_lines,
+            duplication_ratio: ratio(duplicated_lines, total_lines),
+        }
+    }
+}
+
+/// Number of clone classes per status after comparing with a baseline report
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct BaselineSummary {
+    pub new: usize,
+    pub existing: usize,
+    pub resolved: usize,
+}
+
+impl BaselineSummary {
+    pub fn new(records: &[DuplicateReport], resolved: &[DuplicateReport]) -> Self {
+        let count = |status| {
+            records
+                .iter()
+                .filter(|report| report.baseline_status == Some(status))
+                .count()
+        };
+        BaselineSummary {
+            new: count(BaselineStatus::New),
+            existing: count(BaselineStatus::Existing),
+            resolved: resolved.len(),
+        }
+    }
+}
+
+/// Totals over the duplicate blocks of a report
+///
+/// `duplicate_lines` adds up every block, while `unique_duplicate_lines` and the
+/// breakdowns count each line once however many nested or overlapping blocks cover it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Summary {
+    pub duplicate_blocks: usize,
+    pub duplicate_lines: usize,
+    pub duplicate_files: usize,
+    /// Lines in the analyzed files
+    pub total_lines: usize,
+    /// Lines covered by at least one duplicate block
+    pub unique_duplicate_lines: usize,
+    /// `unique_duplicate_lines` over `total_lines`, from 0 to 1
+    pub duplication_ratio: f64,
+    /// Duplicate blocks left out by `reson-ignore` comments, not counted above
+    pub suppressed_blocks: usize,
+    /// Every analyzed file, by path
+    pub files: Vec<Breakdown>,
+    /// Directories below the deepest one holding every file, each including its subdirectories
+    pub directories: Vec<Breakdown>,
+    pub languages: Vec<Breakdown>,
+    /// Set when the run was compared with a baseline report
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineSummary>,
+}
+
+impl Summary {
+    pub fn new(records: &[DuplicateReport], analyzed: &[AnalyzedFile]) -> Self {
+        let blocks = || records.iter().flat_map(|report| &report.blocks);
+
+        let mut ranges: BTreeMap<&str, Vec<(usize, usize)>> = BTreeMap::new();
+        for block in blocks() {
+            ranges
+                .entry(block.source_file.as_str())
+                .or_default()
+                .push((block.start_line_number, block.end_line_number));
+        }
+        let duplicate_files = ranges.len();
+        let covered: BTreeMap<&str, usize> = ranges
+            .into_iter()
+            .map(|(file, ranges)| (file, covered_lines(ranges)))
+            .collect();
+
+        // Files with blocks but no line count still show up, with unknown totals
+        let mut totals: BTreeMap<&str, (usize, usize)> = covered
+            .iter()
+            .map(|(file, &duplicated)| (*file, (0, duplicated)))
+            .collect();
+        let mut languages: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for file in analyzed {
+            let duplicated = covered.get(file.path.as_str()).copied().unwrap_or(0);
+            totals.entry(file.path.as_str()).or_default().0 = file.lines;
+            let language = languages.entry(file.language.as_str()).or_default();
+            language.0 += file.lines;
+            language.1 += duplicated;
+        }
+
+        // Roll each file up to the deepest directory that holds all of them
+        let root = common_directory(totals.keys().copied());
+        let mut directories: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for (file, (total, duplicated)) in &totals {
+            let parents: BTreeSet<String> = Path::new(file)
+                .ancestors()
+                .skip(1)
+                .take_while(|parent| parent.starts_with(&root))
+                .map(|parent| match parent.to_string_lossy().as_ref() {
+                    "" => ".".to_string(),
+                    parent => parent.to_string(),
+                })
+                .collect();
+            for parent in parents {
+                let directory = directories.entry(parent).or_default();
+                directory.0 += total;
+                directory.1 += duplicated;
+            }
+        }
+
+        let total_lines = analyzed.iter().map(|file| file.lines).sum();
+        let unique_duplicate_lines = covered.values().sum();
+        Summary {
+            duplicate_blocks: blocks().count(),
+            duplicate_lines: blocks()
+                .map(|block| block.end_line_number - block.start_line_number + 1)
+                .sum(),
+            duplicate_files,
+            total_lines,
+            unique_duplicate_lines,
+            duplication_ratio: ratio(unique_duplicate_lines, total_lines),
+            suppressed_blocks: 0,
+            files: totals
+                .iter()
+                .map(|(name, &(total, duplicated))| Breakdown::new(name, total, duplicated))
+                .collect(),
+            directories: directories
+                .iter()
+                .map(|(name, &(total, duplicated))| Breakdown::new(name, total, duplicated))
+                .collect(),
+            languages: languages
+                .iter()
+                .map(|(name, &(total, duplicated))| Breakdown::new(name, total, duplicated))
+                .collect(),
+            baseline: None,
+        }
+    }
+}
+
+/// Deepest directory that every file path lies under, empty when they share none
+fn common_directory<'a>(files: impl Iterator<Item = &'a str>) -> PathBuf {
+    let mut common: Option<PathBuf> = None;
+    for file in files {
+        let parent = Path::new(file).parent().unwrap_or(Path::new(""));
+        common = Some(match common {
+            None => parent.to_path_buf(),
+            Some(common) => common
+                .components()
+                .zip(parent.components())
+                .take_while(|(left, right)| left == right)
+                .map(|(component, _)| component)
+                .collect(),
+        });
+    }
+    common.unwrap_or_default()
+}
+
+/// Entries with duplicated lines, most duplicated lines first
+pub fn most_duplicated(breakdowns: &[Breakdown], limit: usize) -> Vec<&Breakdown> {
+    let mut ranked: Vec<&Breakdown> = breakdowns
+        .iter()
+        .filter(|breakdown| breakdown.duplicated_lines > 0)
+        .collect();
+    ranked.sort_by_key(|breakdown| std::cmp::Reverse(breakdown.duplicated_lines));
+    ranked.truncate(limit);
+    ranked
+}
+
+/// Number of lines in the union of inclusive line ranges
+fn covered_lines(mut ranges: Vec<(usize, usize)>) -> usize {
+    ranges.sort_unstable();
+    let mut covered = 0;
+    let mut next_uncovered = 0;
+    for (start, end) in ranges {
+        let start = start.max(next_uncovered);
+        if end >= start {
+            covered += end - start + 1;
+            next_uncovered = end + 1;
+        }
+    }
+    covered
+}
+
+/// Share of duplicated lines, capped at 1 when line counts are unknown or stale
+fn ratio(duplicated: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (duplicated as f64 / total as f64).min(1.0)
+    }
+}
+
+/// Detection options a report was produced with
+///
+/// Missing fields take their defaults, so reports written by older versions still load.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ReportOptions {
+    pub source_path: String,
+    pub languages: Vec<String>,
+    pub excludes: Vec<String>,
+    pub threshold: usize,
+    pub min_ast_nodes: usize,
+    pub clone_type: CloneType,
+    pub granularity: Granularity,
+    pub engine: Engine,
+    pub min_tokens: usize,
+    pub similarity: Option<f64>,
+    pub lsh: bool,
+    pub lsh_bands: usize,
+    pub lsh_rows: usize,
+    pub statement_sequences: bool,
+    pub include_snippets: bool,
+    pub snippet_max_lines: Option<usize>,
+    pub max_file_size: u64,
+    /// Whether files excluded by `.gitignore` and `.resonignore` were scanned too
+    pub no_ignore: bool,
+    /// Report the run was compared with
+    pub baseline: Option<String>,
+    /// Whether only clone classes missing from the baseline were kept
+    pub only_new: bool,
+}
+
+/// Why a file under the source path was not analyzed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Larger than `--max-file-size`
+    TooLarge,
+    /// No supported language, or not one of `--languages`
+    UnsupportedExtension,
+    /// Could not be read or parsed
+    ParseFailure,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: SkipReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Wall-clock seconds spent in each phase of a run
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PhaseTimings {
+    /// Walking the source path and filtering files
+    pub discovery: f64,
+    /// Reading, parsing and fingerprinting or tokenizing files
+    pub parsing: f64,
+    /// Grouping fingerprints and searching for clones
+    pub matching: f64,
+    /// Attaching source snippets
+    pub snippets: f64,
+    pub total: f64,
+}
+
+/// Where and how a report was produced
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Metadata {
+    pub tool_version: String,
+    /// RFC 3339 UTC time at which the report was created
+    pub timestamp: String,
+    pub options: ReportOptions,
+    /// Files that were parsed and compared
+    pub files_scanned: usize,
+    /// Lines in the scanned files
+    pub lines_analyzed: usize,
+    /// Leaf tokens in the scanned files, comments excluded
+    pub tokens_analyzed: usize,
+    pub skipped_files: Vec<SkippedFile>,
+    pub timings: PhaseTimings,
+}
+
+impl Metadata {
+    pub fn new(options: ReportOptions) -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Metadata {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            timestamp: format_timestamp(seconds),
+            options,
+            ..Metadata::default()
+        }
+    }
+}
+
+/// The result of a detection run, shared by every output format
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub summary: Summary,
+    pub records: Vec<DuplicateReport>,
+    /// Clone classes of the baseline report that were not found again
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolved: Vec<DuplicateReport>,
+    /// Blocks left out by `reson-ignore` comments, grouped by the clone class they were in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suppressed: Vec<DuplicateReport>,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+impl Report {
+    pub fn new(
+        records: Vec<DuplicateReport>,
+        analyzed: &[AnalyzedFile],
+        metadata: Metadata,
+    ) -> Self {
+        Report {
+            summary: Summary::new(&records, analyzed),
+            records,
+            resolved: Vec::new(),
+            suppressed: Vec::new(),
+            metadata,
+        }
+    }
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 UTC timestamp
+fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Civil date from a day count, after Howard Hinnant's `civil_from_days`
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::detection_types::{DuplicateBlock, Snippet};
+
+    fn block(source_file: &str, start: usize, end: usize) -> DuplicateBlock {
+        DuplicateBlock {
+            start_line_number: start,
+            end_line_number: end,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        }
+    }
+
+    fn sample_report() -> Report {
+        let mut first = block("src/a.rs", 3, 10);
+        first.symbol_name = Some("mean".to_string());
+        first.symbol_kind = Some("function".to_string());
+        first.context = Some("stats::mean".to_string());
+        first.snippet = Some(Snippet {
+            start_byte: 20,
+            end_byte: 90,
+            start_column: 1,
+            end_column: 2,
+            text: "fn mean() {\n}".to_string(),
+            truncated: true,
+        });
+        let records = vec![
+            DuplicateReport {
+                fingerprint: "aa".to_string(),
+                clone_type: CloneType::Renamed,
+                line_count: 8,
+                similarity: Some(0.9),
+                baseline_status: None,
+                blocks: vec![first, block("src/b.rs", 13, 20)],
+            },
+            DuplicateReport {
+                fingerprint: "bb".to_string(),
+                clone_type: CloneType::Parameterized,
+                line_count: 5,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/a.rs", 30, 34), block("src/a.rs", 40, 44)],
+            },
+        ];
+        let options = ReportOptions {
+            source_path: "src".to_string(),
+            languages: vec!["rust".to_string()],
+            threshold: 5,
+            granularity: Granularity::Function,
+            engine: Engine::Token,
+            similarity: Some(0.8),
+            ..ReportOptions::default()
+        };
+        let mut metadata = Metadata::new(options);
+        metadata.files_scanned = 3;
+        metadata.lines_analyzed = 120;
+        metadata.tokens_analyzed = 900;
+        metadata.skipped_files = vec![SkippedFile {
+            path: "assets/logo.png".to_string(),
+            reason: SkipReason::UnsupportedExtension,
+            detail: None,
+        }];
+        metadata.timings.parsing = 0.25;
+        Report::new(records, &analyzed(), metadata)
+    }
+
+    fn analyzed() -> Vec<AnalyzedFile> {
+        [
+            ("src/a.rs", "rust", 50),
+            ("src/b.rs", "rust", 20),
+            ("src/util/c.py", "python", 30),
+        ]
+        .iter()
+        .map(|(path, language, lines)| AnalyzedFile {
+            path: path.to_string(),
+            language: language.to_string(),
+            lines: *lines,
+        })
+        .collect()
+    }
+
+    fn breakdown(name: &str, total_lines: usize, duplicated_lines: usize) -> Breakdown {
+        Breakdown::new(name, total_lines, duplicated_lines)
+    }
+
+    #[test]
+    fn test_summary_new() {
+        let report = sample_report();
+        assert_eq!(
+            report.summary,
+            Summary {
+                duplicate_blocks: 4,
+                duplicate_lines: 26,
+                duplicate_files: 2,
+                total_lines: 100,
+                unique_duplicate_lines: 26,
+                duplication_ratio: 0.26,
+                suppressed_blocks: 0,
+                files: vec![
+                    breakdown("src/a.rs", 50, 18),
+                    breakdown("src/b.rs", 20, 8),
+                    breakdown("src/util/c.py", 30, 0),
+                ],
+                directories: vec![breakdown("src", 100, 26), breakdown("src/util", 30, 0)],
+                languages: vec![breakdown("python", 30, 0), breakdown("rust", 70, 26)],
+                baseline: None,
+            }
+        );
+        assert_eq!(report.summary.files[0].duplication_ratio, 0.36);
+    }
+
+    #[test]
+    fn test_summary_counts_nested_blocks_once() {
+        let records = vec![
+            DuplicateReport {
+                fingerprint: "outer".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 10,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/a.rs", 1, 10), block("src/b.rs", 1, 10)],
+            },
+            DuplicateReport {
+                fingerprint: "inner".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 4,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/a.rs", 3, 6), block("src/a.rs", 8, 11)],
+            },
+        ];
+        let summary = Summary::new(&records, &analyzed());
+        assert_eq!(summary.duplicate_lines, 28);
+        assert_eq!(summary.unique_duplicate_lines, 21);
+        assert_eq!(summary.files[0], breakdown("src/a.rs", 50, 11));
+        assert_eq!(summary.duplication_ratio, 0.21);
+
+        let top = most_duplicated(&summary.files, 5);
+        assert_eq!(top, vec![&summary.files[0], &summary.files[1]]);
+        assert_eq!(most_duplicated(&summary.files, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_baseline_summary() {
+        let mut records = sample_report().records;
+        records[0].baseline_status = Some(BaselineStatus::New);
+        records[1].baseline_status = Some(BaselineStatus::Existing);
+        let resolved = vec![records[1].clone()];
+        assert_eq!(
+            BaselineSummary::new(&records, &resolved),
+            BaselineSummary {
+                new: 1,
+                existing: 1,
+                resolved: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_common_directory() {
+        let common = |files: &[&str]| common_directory(files.iter().copied());
+        assert_eq!(common(&[]), PathBuf::new());
+        assert_eq!(
+            common(&["src/a/x.rs", "src/a/b/y.rs"]),
+            PathBuf::from("src/a")
+        );
+        assert_eq!(common(&["./src/x.rs", "./tests/y.rs"]), PathBuf::from("."));
+        assert_eq!(
+            common(&["/repo/src/x.rs", "/repo/srcs/y.rs"]),
+            PathBuf::from("/repo")
+        );
+        assert_eq!(common(&["x.rs", "src/y.rs"]), PathBuf::new());
+    }
+
+    #[test]
+    fn test_covered_lines() {
+        assert_eq!(covered_lines(vec![]), 0);
+        assert_eq!(covered_lines(vec![(5, 9), (1, 3), (2, 4), (6, 7)]), 9);
+        assert_eq!(covered_lines(vec![(1, 1), (2, 2)]), 2);
+        assert_eq!(covered_lines(vec![(10, 20), (1, 5), (15, 25), (6, 6)]), 22);
+        assert_eq!(covered_lines(vec![(1, 2), (4, 5)]), 4);
+        assert_eq!(ratio(3, 0), 0.0);
+        assert_eq!(ratio(12, 10), 1.0);
+    }
+
+    #[test]
+    fn test_report_json_round_trip() {
+        let report = sample_report();
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.starts_with("{\"summary\":{\"duplicateBlocks\":4,"));
+        assert!(json.contains("\"tool_version\":\"1."));
+        assert!(json.contains(
+            "\"skipped_files\":[{\"path\":\"assets/logo.png\",\"reason\":\"unsupported_extension\"}]"
+        ));
+
+        let parsed: Report = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    #[test]
+    fn test_report_without_metadata() {
+        let json = r#"{
+            "summary": {"duplicateBlocks": 0, "duplicateLines": 0, "duplicateFiles": 0},
+            "records": []
+        }"#;
+        let parsed: Report = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed, Report::default());
+    }
+
+    #[test]
+    fn test_report_from_older_version() {
+        // Records without clone types or symbols, options without the later settings
+        let json = r#"{
+            "summary": {"duplicateBlocks": 2, "duplicateLines": 12, "duplicateFiles": 2},
+            "records": [{
+                "fingerprint": "aa",
+                "line_count": 6,
+                "blocks": [
+                    {"start_line_number": 1, "end_line_number": 6, "source_file": "src/a.rs"},
+                    {"start_line_number": 3, "end_line_number": 8, "source_file": "src/b.rs"}
+                ]
+            }],
+            "metadata": {
+                "tool_version": "1.3.0",
+                "options": {"source_path": "src", "threshold": 5, "max_file_size": 1048576}
+            }
+        }"#;
+        let parsed: Report = serde_json::from_str(json).unwrap();
+
+        let record = &parsed.records[0];
+        assert_eq!(record.clone_type, CloneType::Parameterized);
+        assert_eq!(record.similarity, None);
+        assert_eq!(record.blocks[1].source_file, "src/b.rs");
+        assert_eq!(record.blocks[1].symbol_name, None);
+        assert_eq!(parsed.summary.duplicate_lines, 12);
+        let options = &parsed.metadata.options;
+        assert_eq!(options.source_path, "src");
+        assert_eq!(options.threshold, 5);
+        assert_eq!(options.clone_type, CloneType::Parameterized);
+        assert!(!options.no_ignore);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format_timestamp(4_107_542_399), "2100-02-28T23:59:59Z");
+    }
+}
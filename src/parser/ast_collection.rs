@@ -1,4 +1,6 @@
+use crate::models::code_types::{CloneType, Suppressions, Token};
 use blake3::Hasher;
+use std::collections::HashMap;
 use tree_sitter::Node;
 
 pub fn compute_ast_fingerprint(ast_representation: &str) -> String {
@@ -14,30 +16,450 @@ pub fn compute_ast_fingerprint(ast_representation: &str) -> String {
     fingerprint
 }
 
-/// Recursively collect the content of all nodes in the AST
-pub fn collect_ast_content(node: Node, source: &str) -> (String, usize) {
+/// Digest of a single subtree, combined bottom-up from the digests of its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubtreeDigest {
+    pub hash: blake3::Hash,
+    pub ast_nodes: usize,
+    pub has_text: bool,
+}
+
+impl SubtreeDigest {
+    pub fn fingerprint(&self) -> String {
+        self.hash.to_hex().to_string()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ast_nodes == 0 && !self.has_text
+    }
+}
+
+fn is_fingerprinted_node(node: &Node) -> bool {
+    node.is_named() && !node.kind().contains("comment")
+}
+
+fn is_identifier_kind(kind: &str) -> bool {
+    kind.ends_with("identifier")
+}
+
+fn is_literal_kind(kind: &str) -> bool {
+    kind.ends_with("literal")
+        || matches!(
+            kind,
+            "string"
+                | "concatenated_string"
+                | "template_string"
+                | "number"
+                | "integer"
+                | "float"
+                | "true"
+                | "false"
+                | "null"
+                | "none"
+                | "nil"
+                | "undefined"
+                | "nullptr"
+        )
+}
+
+/// Source text that the given clone type keeps for this node, if any
+fn hashed_text<'a>(node: &Node, source: &'a str, clone_type: CloneType) -> Option<&'a str> {
+    let kind = node.kind();
+    if kind.contains("comment") {
+        return None;
+    }
+
+    let keep = if node.is_named() && is_literal_kind(kind) {
+        clone_type.keeps_literals()
+    } else if node.child_count() > 0 {
+        false
+    } else if node.is_named() && is_identifier_kind(kind) {
+        clone_type.keeps_identifiers()
+    } else {
+        clone_type.keeps_tokens()
+    };
+
+    if keep {
+        Some(&source[node.start_byte()..node.end_byte()])
+    } else {
+        None
+    }
+}
+
+fn update_with_str(hasher: &mut Hasher, value: &str) {
+    hasher.update(&(value.len() as u32).to_le_bytes());
+    hasher.update(value.as_bytes());
+}
+
+/// Compute Merkle-style digests for every node of the tree in a single post-order pass.
+///
+/// The hash of a named node combines its kind with the field name and hash of each child,
+/// framed by open/close markers and the child count, so the encoding captures the tree
+/// shape rather than a flat list of kinds. Each node is visited exactly once regardless
+/// of how many ancestors are fingerprinted. Anonymous tokens and comments are
+/// transparent: they only contribute their children, plus their own text when the
+/// clone type keeps tokens.
+pub fn compute_subtree_digests(
+    root: Node,
+    source: &str,
+    clone_type: CloneType,
+) -> HashMap<usize, SubtreeDigest> {
+    let mut digests: HashMap<usize, SubtreeDigest> = HashMap::new();
+    let mut stack = vec![(root, false)];
+
+    while let Some((node, children_done)) = stack.pop() {
+        if !children_done {
+            stack.push((node, true));
+            for child in node.children(&mut node.walk()) {
+                stack.push((child, false));
+            }
+            continue;
+        }
+
+        let counted = is_fingerprinted_node(&node);
+        let mut hasher = Hasher::new();
+        let mut ast_nodes = usize::from(counted);
+        let mut arity: u32 = 0;
+        if counted {
+            hasher.update(b"(");
+            update_with_str(&mut hasher, node.kind());
+        } else {
+            hasher.update(b"[");
+        }
+        let text = hashed_text(&node, source, clone_type);
+        if let Some(text) = text {
+            hasher.update(b"=");
+            update_with_str(&mut hasher, text.trim());
+        }
+
+        let mut cursor = node.walk();
+        if cursor.goto_first_child() {
+            loop {
+                let child_digest = digests[&cursor.node().id()];
+                if !child_digest.is_empty() {
+                    match cursor.field_name() {
+                        Some(field_name) => {
+                            hasher.update(b"F");
+                            update_with_str(&mut hasher, field_name);
+                        }
+                        None => {
+                            hasher.update(b"-");
+                        }
+                    }
+                    hasher.update(child_digest.hash.as_bytes());
+                    ast_nodes += child_digest.ast_nodes;
+                    arity += 1;
+                }
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+
+        hasher.update(if counted { b")" } else { b"]" });
+        hasher.update(&arity.to_le_bytes());
+
+        digests.insert(
+            node.id(),
+            SubtreeDigest {
+                hash: hasher.finalize(),
+                ast_nodes,
+                has_text: text.is_some(),
+            },
+        );
+    }
+
+    digests
+}
+
+/// Preorder sequence of hashed node kinds for the fingerprinted nodes of a tree
+///
+/// Each entry also covers the leaf text that the clone type keeps for the node and for
+/// its anonymous tokens, so that near-miss similarity follows `--clone-type` as the
+/// fingerprints do.
+///
+/// Nodes are listed in document order, so the nodes of every subtree form a contiguous
+/// slice of the sequence.
+pub struct KindSequence {
+    pub kinds: Vec<u32>,
+    offsets: HashMap<usize, usize>,
+}
+
+impl KindSequence {
+    /// The kinds of the fingerprinted nodes inside the given subtree
+    pub fn subtree(&self, node: &Node, digest: &SubtreeDigest) -> &[u32] {
+        match self.offsets.get(&node.id()) {
+            Some(&offset) => &self.kinds[offset..offset + digest.ast_nodes],
+            None => &[],
+        }
+    }
+}
+
+/// Stable 32-bit hash of a node kind, so equal kinds match across grammars
+fn hash_kind(kind: &str) -> u32 {
+    let hash = blake3::hash(kind.as_bytes());
+    let bytes = hash.as_bytes();
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Fold the leaf text kept for a node and its anonymous tokens into its kind hash
+fn hash_kept_text(kind_hash: u32, node: &Node, source: &str, clone_type: CloneType) -> u32 {
+    let mut cursor = node.walk();
+    let texts: Vec<&str> = hashed_text(node, source, clone_type)
+        .into_iter()
+        .chain(
+            node.children(&mut cursor)
+                .filter(|child| !child.is_named())
+                .filter_map(|child| hashed_text(&child, source, clone_type)),
+        )
+        .collect();
+    if texts.is_empty() {
+        return kind_hash;
+    }
+
+    let mut hasher = Hasher::new();
+    hasher.update(&kind_hash.to_le_bytes());
+    for text in texts {
+        update_with_str(&mut hasher, text.trim());
+    }
+    let bytes = hasher.finalize();
+    let bytes = bytes.as_bytes();
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+pub fn collect_kind_sequence(root: Node, source: &str, clone_type: CloneType) -> KindSequence {
+    let mut kinds = Vec::new();
+    let mut offsets = HashMap::new();
+    let mut kind_hashes: HashMap<u16, u32> = HashMap::new();
+    let mut stack = vec![root];
+
+    while let Some(node) = stack.pop() {
+        if is_fingerprinted_node(&node) {
+            offsets.insert(node.id(), kinds.len());
+            let kind_hash = *kind_hashes
+                .entry(node.kind_id())
+                .or_insert_with(|| hash_kind(node.kind()));
+            kinds.push(hash_kept_text(kind_hash, &node, source, clone_type));
+        }
+
+        let children: Vec<Node> = node.children(&mut node.walk()).collect();
+        stack.extend(children.into_iter().rev());
+    }
+
+    KindSequence { kinds, offsets }
+}
+
+/// The leaf nodes that make up the token stream, in source order
+///
+/// Literals stay whole even where the grammar splits them into parts. Comments and
+/// zero-width tokens inserted by error recovery are skipped.
+fn token_nodes(root: Node) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![root];
+
+    while let Some(node) = stack.pop() {
+        let kind = node.kind();
+        if kind.contains("comment") {
+            continue;
+        }
+        if node.child_count() > 0 && !(node.is_named() && is_literal_kind(kind)) {
+            let children: Vec<Node> = node.children(&mut node.walk()).collect();
+            stack.extend(children.into_iter().rev());
+            continue;
+        }
+        if node.start_byte() == node.end_byte() {
+            continue;
+        }
+        nodes.push(node);
+    }
+
+    nodes
+}
+
+/// Number of tokens `collect_tokens` would produce, without hashing them
+pub fn count_tokens(root: Node) -> usize {
+    token_nodes(root).len()
+}
+
+/// Flatten the tree into the leaf tokens compared by the token engine
+///
+/// Each token only keeps the text its clone type keeps, so renamed identifiers still
+/// line up.
+pub fn collect_tokens(root: Node, source: &str, clone_type: CloneType) -> Vec<Token> {
+    token_nodes(root)
+        .into_iter()
+        .map(|node| {
+            let mut hasher = Hasher::new();
+            update_with_str(&mut hasher, node.kind());
+            if let Some(text) = hashed_text(&node, source, clone_type) {
+                hasher.update(b"=");
+                update_with_str(&mut hasher, text.trim());
+            }
+            let hash = hasher.finalize();
+            let mut symbol = [0; 8];
+            symbol.copy_from_slice(&hash.as_bytes()[..8]);
+
+            Token {
+                symbol: u64::from_le_bytes(symbol),
+                start_byte: node.start_byte(),
+                end_byte: node.end_byte(),
+                start_line: node.start_position().row + 1,
+                end_line: node.end_position().row + 1,
+            }
+        })
+        .collect()
+}
+
+/// Prefix shared by every comment directive, checked first to skip files without any
+const DIRECTIVE_PREFIX: &str = "reson-ignore";
+const IGNORE_FILE: &str = "reson-ignore-file";
+const IGNORE_NEXT: &str = "reson-ignore-next";
+const IGNORE_START: &str = "reson-ignore-start";
+const IGNORE_END: &str = "reson-ignore-end";
+const DIRECTIVES: [&str; 4] = [IGNORE_FILE, IGNORE_NEXT, IGNORE_START, IGNORE_END];
+
+/// The directive a comment starts with, after its comment markers
+///
+/// Only the leading word counts, so prose that mentions a directive is not one.
+fn comment_directive(text: &str) -> Option<&str> {
+    let body = text.trim_start_matches(|c: char| "/*#!-;".contains(c) || c.is_whitespace());
+    let end = body
+        .find(|c: char| !(c.is_ascii_lowercase() || c == '-'))
+        .unwrap_or(body.len());
+    DIRECTIVES
+        .into_iter()
+        .find(|directive| *directive == &body[..end])
+}
+
+/// The next named sibling of a comment in the same block, looking past other comments
+fn next_node_after(comment: Node) -> Option<Node> {
+    let mut sibling = comment.next_named_sibling();
+    while let Some(node) = sibling {
+        if !node.kind().contains("comment") {
+            return Some(node);
+        }
+        sibling = node.next_named_sibling();
+    }
+    None
+}
+
+/// Read the `reson-ignore` directives from the comments of a file
+///
+/// `reson-ignore-file` suppresses the whole file, `reson-ignore-next` the node that
+/// follows the comment in the same block, and `reson-ignore-start` the lines up to the
+/// next `reson-ignore-end`, or to the end of the file when there is none. A directive
+/// must be the first word of its comment. The directives work in every language since
+/// they only rely on the grammar's comment nodes.
+pub fn collect_suppressions(root: Node, source: &str) -> Suppressions {
+    let mut suppressions = Suppressions::default();
+    if !source.contains(DIRECTIVE_PREFIX) {
+        return suppressions;
+    }
+
+    let mut open_range: Option<usize> = None;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if !node.kind().contains("comment") {
+            let children: Vec<Node> = node.children(&mut node.walk()).collect();
+            stack.extend(children.into_iter().rev());
+            continue;
+        }
+
+        match comment_directive(&source[node.start_byte()..node.end_byte()]) {
+            Some(IGNORE_FILE) => suppressions.file = true,
+            Some(IGNORE_START) => {
+                open_range.get_or_insert(node.start_position().row + 1);
+            }
+            Some(IGNORE_END) => {
+                if let Some(start) = open_range.take() {
+                    suppressions
+                        .ranges
+                        .push((start, node.end_position().row + 1));
+                }
+            }
+            Some(IGNORE_NEXT) => {
+                if let Some(next) = next_node_after(node) {
+                    suppressions
+                        .ranges
+                        .push((next.start_position().row + 1, next.end_position().row + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = open_range {
+        suppressions
+            .ranges
+            .push((start, root.end_position().row + 1));
+    }
+
+    suppressions
+}
+
+/// Collect an S-expression of the AST, including field names and node boundaries
+///
+/// This is the readable counterpart of `compute_subtree_digests`, which the tests check
+/// the digests against. Leaf text kept by the clone type is rendered as a quoted string.
+#[cfg(test)]
+fn collect_ast_content(node: Node, source: &str, clone_type: CloneType) -> (String, usize) {
     let mut ast_output = String::new();
-    let mut stack = vec![node];
     let mut line_count = 0;
+    let mut cursor = node.walk();
+    let mut depth = 0;
 
-    while let Some(current_node) = stack.pop() {
-        if current_node.is_named() && !current_node.kind().contains("comment") {
+    loop {
+        let current_node = cursor.node();
+        if is_fingerprinted_node(&current_node) {
             let node_text = &source[current_node.start_byte()..current_node.end_byte()];
             log::debug!(
                 "Node type: {:?}, text: {:?}",
                 current_node.kind(),
                 node_text
             );
-            ast_output.push_str(&format!("{:?}\n", current_node.kind()));
+            if !ast_output.is_empty() {
+                ast_output.push('\n');
+            }
+            ast_output.push_str(&"  ".repeat(depth));
+            if let Some(field_name) = cursor.field_name() {
+                ast_output.push_str(field_name);
+                ast_output.push_str(": ");
+            }
+            ast_output.push('(');
+            ast_output.push_str(current_node.kind());
             line_count += 1;
         }
+        if let Some(text) = hashed_text(&current_node, source, clone_type) {
+            if is_fingerprinted_node(&current_node) {
+                ast_output.push(' ');
+            } else {
+                if !ast_output.is_empty() {
+                    ast_output.push('\n');
+                }
+                ast_output.push_str(&"  ".repeat(depth));
+            }
+            ast_output.push_str(&format!("{:?}", text.trim()));
+        }
 
-        for child in current_node.children(&mut current_node.walk()) {
-            stack.push(child);
+        if cursor.goto_first_child() {
+            depth += 1;
+            continue;
         }
-    }
 
-    (ast_output, line_count)
+        // Close finished nodes until there is a sibling left to visit
+        loop {
+            if is_fingerprinted_node(&cursor.node()) {
+                ast_output.push(')');
+            }
+            if depth == 0 {
+                return (ast_output, line_count);
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            cursor.goto_parent();
+            depth -= 1;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -66,6 +488,429 @@ mod tests {
         assert_eq!(fingerprint.len(), 64); // Blake3 hash length in hex is 64 characters
     }
 
+    fn suppressions_of(language: tree_sitter::Language, content: &str) -> Suppressions {
+        let mut parser = Parser::new();
+        parser
+            .set_language(language)
+            .expect("Failed to set language");
+        let tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+        collect_suppressions(tree.root_node(), content)
+    }
+
+    #[test]
+    fn test_collect_suppressions() {
+        let content = r#"fn first() {
+    // reson-ignore-next
+    let table = [
+        1, 2, 3,
+    ];
+    let a = 0; // reson-ignore-next
+}
+
+// reson-ignore-start: generated handlers
+fn second() {}
+fn third() {}
+/* reson-ignore-end */
+
+fn fourth() {
+    // reson-ignore-start
+    let b = 1;
+}
+"#;
+        let suppressions = suppressions_of(tree_sitter_rust::language(), content);
+        assert!(!suppressions.file);
+        // The trailing comment on line 6 has no sibling left in the block, so `second`
+        // on line 10 is only covered by the start/end range
+        assert_eq!(suppressions.ranges, vec![(3, 5), (9, 12), (15, 18)]);
+    }
+
+    #[test]
+    fn test_collect_suppressions_needs_leading_directive() {
+        let content = r#"// Use reson-ignore-next sparingly
+fn first() {}
+
+/// reson-ignore-next
+fn second() {}
+
+/*reson-ignore-file*/
+"#;
+        let suppressions = suppressions_of(tree_sitter_rust::language(), content);
+        assert!(suppressions.file);
+        assert_eq!(suppressions.ranges, vec![(5, 5)]);
+
+        assert_eq!(comment_directive("# reson-ignore-end"), Some(IGNORE_END));
+        assert_eq!(comment_directive("// reson-ignored"), None);
+        assert_eq!(comment_directive("// TODO: reson-ignore-next"), None);
+    }
+
+    #[test]
+    fn test_collect_suppressions_in_other_languages() {
+        let python =
+            "# reson-ignore-next\ndef handler():\n    return 1\n\ndef other():\n    pass\n";
+        let suppressions = suppressions_of(tree_sitter_python::language(), python);
+        assert_eq!(suppressions.ranges, vec![(2, 3)]);
+
+        let c = "/* reson-ignore-file */\nint main() { return 0; }\n";
+        assert!(suppressions_of(tree_sitter_c::language(), c).file);
+
+        // Directives must be in comments, not in strings
+        let go = "package main\n\nvar s = \"reson-ignore-file\"\n";
+        assert!(suppressions_of(tree_sitter_go::language(), go).is_empty());
+    }
+
+    #[test]
+    fn test_compute_subtree_digests_is_deterministic() {
+        let content = r#"
+        int first() {
+            int a = 0;
+            return a;
+        }
+
+        int second() {
+            int b = 1;
+            return b;
+        }
+        "#;
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_c::language())
+            .expect("Failed to set language");
+        let tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+        let reparsed_tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+
+        let digests = compute_subtree_digests(tree.root_node(), content, CloneType::Parameterized);
+        let reparsed_digests =
+            compute_subtree_digests(reparsed_tree.root_node(), content, CloneType::Parameterized);
+        let root = tree.root_node();
+        let first = root.named_child(0).unwrap();
+        let second = root.named_child(1).unwrap();
+
+        assert_eq!(
+            digests[&root.id()].fingerprint(),
+            reparsed_digests[&reparsed_tree.root_node().id()].fingerprint()
+        );
+        assert_eq!(digests[&first.id()].hash, digests[&second.id()].hash);
+        assert_eq!(digests[&first.id()].fingerprint().len(), 64);
+    }
+
+    #[test]
+    fn test_compute_subtree_digests_counts_match_collect_ast_content() {
+        let content = r#"
+        fn main() {
+            // This is a comment
+            let a = 0;
+            println!("{}", a);
+        }
+        "#;
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_rust::language())
+            .expect("Failed to set language");
+        let tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+
+        let digests = compute_subtree_digests(tree.root_node(), content, CloneType::Parameterized);
+        let mut cursor = tree.walk();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            let (_representation, ast_lines) =
+                collect_ast_content(node, content, CloneType::Parameterized);
+            assert_eq!(digests[&node.id()].ast_nodes, ast_lines);
+            stack.extend(node.children(&mut cursor));
+        }
+    }
+
+    /// Flat kind list in stack-pop order, as produced by the original fingerprint encoding
+    fn legacy_kind_sequence(node: Node) -> String {
+        let mut output = String::new();
+        let mut stack = vec![node];
+        while let Some(current_node) = stack.pop() {
+            if is_fingerprinted_node(&current_node) {
+                output.push_str(&format!("{:?}\n", current_node.kind()));
+            }
+            for child in current_node.children(&mut current_node.walk()) {
+                stack.push(child);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_subtree_digests_distinguish_nesting() {
+        let nested = r#"
+        void run() {
+            {
+                first();
+                second();
+            }
+        }
+        "#;
+        let flattened = r#"
+        void run() {
+            first();
+            {
+                second();
+            }
+        }
+        "#;
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_c::language())
+            .expect("Failed to set language");
+        let nested_tree = parser.parse(nested, None).expect("Failed to parse content");
+        let flattened_tree = parser
+            .parse(flattened, None)
+            .expect("Failed to parse content");
+        let nested_root = nested_tree.root_node();
+        let flattened_root = flattened_tree.root_node();
+
+        // The old flat encoding could not tell these two shapes apart
+        assert_eq!(
+            legacy_kind_sequence(nested_root),
+            legacy_kind_sequence(flattened_root)
+        );
+
+        let nested_digests = compute_subtree_digests(nested_root, nested, CloneType::Parameterized);
+        let flattened_digests =
+            compute_subtree_digests(flattened_root, flattened, CloneType::Parameterized);
+        assert_eq!(
+            nested_digests[&nested_root.id()].ast_nodes,
+            flattened_digests[&flattened_root.id()].ast_nodes
+        );
+        assert_ne!(
+            nested_digests[&nested_root.id()].hash,
+            flattened_digests[&flattened_root.id()].hash
+        );
+        assert_ne!(
+            collect_ast_content(nested_root, nested, CloneType::Parameterized).0,
+            collect_ast_content(flattened_root, flattened, CloneType::Parameterized).0
+        );
+    }
+
+    fn root_fingerprint(content: &str, clone_type: CloneType) -> String {
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_java::language())
+            .expect("Failed to set language");
+        let tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+        let root = tree.root_node();
+        compute_subtree_digests(root, content, clone_type)[&root.id()].fingerprint()
+    }
+
+    #[test]
+    fn test_subtree_digests_respect_clone_type() {
+        let original = "class A { int total() { return count + 1; } }";
+        let renamed = "class B { int sum() { return size + 1; } }";
+        let relabelled = "class B { int sum() { return size + 2; } }";
+        let reformatted = "class A {\n  int total() {\n    return count + 1; // done\n  }\n}";
+
+        assert_eq!(
+            root_fingerprint(original, CloneType::Exact),
+            root_fingerprint(reformatted, CloneType::Exact)
+        );
+        assert_ne!(
+            root_fingerprint(original, CloneType::Exact),
+            root_fingerprint(renamed, CloneType::Exact)
+        );
+
+        assert_eq!(
+            root_fingerprint(original, CloneType::Renamed),
+            root_fingerprint(renamed, CloneType::Renamed)
+        );
+        assert_ne!(
+            root_fingerprint(renamed, CloneType::Renamed),
+            root_fingerprint(relabelled, CloneType::Renamed)
+        );
+
+        assert_eq!(
+            root_fingerprint(original, CloneType::Parameterized),
+            root_fingerprint(relabelled, CloneType::Parameterized)
+        );
+    }
+
+    #[test]
+    fn test_exact_clone_type_keeps_operators() {
+        let addition = "class A { int f() { return a + b; } }";
+        let subtraction = "class A { int f() { return a - b; } }";
+
+        assert_ne!(
+            root_fingerprint(addition, CloneType::Exact),
+            root_fingerprint(subtraction, CloneType::Exact)
+        );
+        assert_eq!(
+            root_fingerprint(addition, CloneType::Parameterized),
+            root_fingerprint(subtraction, CloneType::Parameterized)
+        );
+    }
+
+    #[test]
+    fn test_collect_ast_content_renders_kept_text() {
+        let content = "fn main() { let total = 42; }";
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_rust::language())
+            .expect("Failed to set language");
+        let tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+
+        let (exact, _) = collect_ast_content(tree.root_node(), content, CloneType::Exact);
+        let (renamed, _) = collect_ast_content(tree.root_node(), content, CloneType::Renamed);
+
+        assert!(exact.contains("(identifier \"total\")"));
+        assert!(exact.contains("(integer_literal \"42\")"));
+        assert!(!renamed.contains("\"total\""));
+        assert!(renamed.contains("(integer_literal \"42\")"));
+    }
+
+    #[test]
+    fn test_collect_kind_sequence_follows_collect_ast_content() {
+        let content = r#"
+        fn main() {
+            // This is a comment
+            let a = 0;
+            println!("{}", a);
+        }
+        "#;
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_rust::language())
+            .expect("Failed to set language");
+        let tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+        let root = tree.root_node();
+
+        let sequence = collect_kind_sequence(root, content, CloneType::Parameterized);
+        let digests = compute_subtree_digests(root, content, CloneType::Parameterized);
+        let (ast_representation, ast_lines) =
+            collect_ast_content(root, content, CloneType::Parameterized);
+        let printed_kinds: Vec<u32> = ast_representation
+            .lines()
+            .map(|line| {
+                let kind = line.trim_start().rsplit('(').next().unwrap();
+                hash_kind(kind.trim_end_matches(')'))
+            })
+            .collect();
+
+        assert_eq!(sequence.kinds.len(), ast_lines);
+        assert_eq!(sequence.kinds, printed_kinds);
+
+        let function = root.named_child(0).unwrap();
+        let function_kinds = sequence.subtree(&function, &digests[&function.id()]);
+        assert_eq!(function_kinds.len(), digests[&function.id()].ast_nodes);
+        assert_eq!(function_kinds[0], hash_kind("function_item"));
+    }
+
+    #[test]
+    fn test_collect_kind_sequence_follows_clone_type() {
+        let content = "int f() { return a + 1; }\nint g() { return b - 2; }\n";
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_c::language()).unwrap();
+        let tree = parser.parse(content, None).unwrap();
+        let root = tree.root_node();
+        let (first, second) = (root.named_child(0).unwrap(), root.named_child(1).unwrap());
+
+        let kinds_of = |clone_type| {
+            let sequence = collect_kind_sequence(root, content, clone_type);
+            let digests = compute_subtree_digests(root, content, clone_type);
+            (
+                sequence.subtree(&first, &digests[&first.id()]).to_vec(),
+                sequence.subtree(&second, &digests[&second.id()]).to_vec(),
+            )
+        };
+
+        let (left, right) = kinds_of(CloneType::Parameterized);
+        assert_eq!(left, right);
+        // Renamed keeps the literals and the operator, exact the identifiers too
+        let (left, right) = kinds_of(CloneType::Renamed);
+        assert_ne!(left, right);
+        assert_eq!(left[..4], right[..4]);
+        let (exact_left, _) = kinds_of(CloneType::Exact);
+        assert_ne!(exact_left, left);
+    }
+
+    fn token_symbols(content: &str, clone_type: CloneType) -> Vec<u64> {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_java::language()).unwrap();
+        let tree = parser.parse(content, None).unwrap();
+        collect_tokens(tree.root_node(), content, clone_type)
+            .iter()
+            .map(|token| token.symbol)
+            .collect()
+    }
+
+    #[test]
+    fn test_collect_tokens_normalizes_by_clone_type() {
+        let original = "class A { int f(int a) { return a + \"x\".length(); } }";
+        let renamed = "class B { int g(int b) { return b + \"x\".length(); } }";
+        let relabeled = "class B { int g(int b) { return b + \"yz\".length(); } }";
+
+        let tokens = token_symbols(original, CloneType::Exact);
+        // class A { int f ( int a ) { return a + "x" . length ( ) ; } }
+        assert_eq!(tokens.len(), 21);
+        assert_ne!(tokens, token_symbols(renamed, CloneType::Exact));
+        assert_eq!(
+            token_symbols(original, CloneType::Renamed),
+            token_symbols(renamed, CloneType::Renamed)
+        );
+        assert_ne!(
+            token_symbols(renamed, CloneType::Renamed),
+            token_symbols(relabeled, CloneType::Renamed)
+        );
+        assert_eq!(
+            token_symbols(renamed, CloneType::Parameterized),
+            token_symbols(relabeled, CloneType::Parameterized)
+        );
+    }
+
+    #[test]
+    fn test_collect_tokens_skips_comments_and_keeps_lines() {
+        let content =
+            "class A {\n    // note\n    int f() {\n        return 1; /* one */\n    }\n}";
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_java::language()).unwrap();
+        let tree = parser.parse(content, None).unwrap();
+        let tokens = collect_tokens(tree.root_node(), content, CloneType::Parameterized);
+
+        let lines: Vec<usize> = tokens.iter().map(|token| token.start_line).collect();
+        assert_eq!(lines, vec![1, 1, 1, 3, 3, 3, 3, 3, 4, 4, 4, 5, 6]);
+        assert_eq!(count_tokens(tree.root_node()), tokens.len());
+    }
+
+    #[test]
+    fn test_collect_ast_content_includes_fields_and_boundaries() {
+        let content = "fn main() { let a = 0; }";
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_rust::language())
+            .expect("Failed to set language");
+        let tree = parser
+            .parse(content, None)
+            .expect("Failed to parse content");
+
+        let (ast_representation, ast_lines) =
+            collect_ast_content(tree.root_node(), content, CloneType::Parameterized);
+
+        assert!(ast_representation.starts_with("(source_file"));
+        assert!(ast_representation.contains("name: (identifier)"));
+        assert!(ast_representation.contains("body: (block"));
+        assert_eq!(
+            ast_representation.matches('(').count(),
+            ast_representation.matches(')').count()
+        );
+        assert_eq!(ast_representation.lines().count(), ast_lines);
+    }
+
     #[test]
     fn test_collect_ast_content_with_comments_c() {
         let content = r#"
@@ -100,10 +945,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
@@ -144,10 +990,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
@@ -190,10 +1037,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
@@ -234,10 +1082,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
@@ -278,10 +1127,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
@@ -312,10 +1162,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
@@ -364,10 +1215,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
@@ -408,10 +1260,11 @@ mod tests {
             .expect("Failed to parse content without comments");
 
         let (ast_representation, _ast_lines) =
-            collect_ast_content(parsed_tree.root_node(), content);
+            collect_ast_content(parsed_tree.root_node(), content, CloneType::Parameterized);
         let (ast_representation_without_comments, _ast_lines) = collect_ast_content(
             parsed_tree_without_comments.root_node(),
             content_without_comments,
+            CloneType::Parameterized,
         );
 
         assert!(!ast_representation.contains("comment"));
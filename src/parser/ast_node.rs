@@ -1,3 +1,4 @@
+use crate::models::code_types::Symbol;
 use reson::{LARGE_ARRAY_THRESHOLD, LARGE_CONTENT_LENGTH_THRESHOLD};
 
 fn is_large_array(
@@ -25,7 +26,7 @@ fn is_large_array(
     if normalized_content.len() > array_size_threshold
         && parts
             .iter()
-            .all(|p| p.trim().len() > 0 && !p.trim().contains(' '))
+            .all(|p| !p.trim().is_empty() && !p.trim().contains(' '))
     {
         log::debug!("Large array detected: {:?}", content);
         return true;
@@ -34,6 +35,133 @@ fn is_large_array(
     false
 }
 
+/// Nodes whose named children form a statement sequence, across the supported grammars
+pub fn is_statement_container(node: &tree_sitter::Node) -> bool {
+    matches!(
+        node.kind(),
+        "block" | "compound_statement" | "statement_block"
+    )
+}
+
+/// Whether a function-like node is declared in the body of a class, struct, impl or trait
+fn is_member_function(node: &tree_sitter::Node) -> bool {
+    let mut current = node.parent();
+    while let Some(parent) = current {
+        match parent.kind() {
+            "block"
+            | "declaration_list"
+            | "field_declaration_list"
+            | "decorated_definition"
+            | "template_declaration" => current = parent.parent(),
+            kind => {
+                return matches!(
+                    kind,
+                    "class_definition"
+                        | "class_specifier"
+                        | "struct_specifier"
+                        | "impl_item"
+                        | "trait_item"
+                )
+            }
+        }
+    }
+    false
+}
+
+/// The name of a function-like node, or of the variable an anonymous function is bound to
+fn function_name<'a>(node: &tree_sitter::Node<'a>) -> Option<tree_sitter::Node<'a>> {
+    if let Some(name) = node.child_by_field_name("name") {
+        return Some(name);
+    }
+
+    // C and C++ nest the name inside (pointer, reference, function) declarators
+    if let Some(mut declarator) = node.child_by_field_name("declarator") {
+        while let Some(inner) = declarator.child_by_field_name("declarator") {
+            declarator = inner;
+        }
+        return Some(declarator);
+    }
+
+    let parent = node.parent()?;
+    match parent.kind() {
+        "variable_declarator" => parent.child_by_field_name("name"),
+        "let_declaration" => parent.child_by_field_name("pattern"),
+        "assignment" | "assignment_expression" => parent.child_by_field_name("left"),
+        "pair" => parent.child_by_field_name("key"),
+        _ => None,
+    }
+}
+
+/// Name and kind of a function-like node, across the supported grammars
+pub fn function_symbol(node: &tree_sitter::Node, source: &str) -> Option<Symbol> {
+    // Keyword tokens such as JavaScript's `function` share their kind with the node
+    if !node.is_named() {
+        return None;
+    }
+    let kind = match node.kind() {
+        "method_declaration" | "method_definition" => "method",
+        "constructor_declaration" => "constructor",
+        "arrow_function" | "lambda_expression" | "closure_expression" | "lambda"
+        | "func_literal" => "closure",
+        "function_item"
+        | "function_definition"
+        | "function_declaration"
+        | "generator_function_declaration"
+        | "function"
+        | "function_expression" => {
+            if is_member_function(node) {
+                "method"
+            } else {
+                "function"
+            }
+        }
+        _ => return None,
+    };
+    let name =
+        function_name(node).map(|name| source[name.start_byte()..name.end_byte()].to_string());
+
+    Some(Symbol { name, kind })
+}
+
+/// The named type under references and generic arguments, so that `&Cache<T>` is `Cache`
+fn base_type(mut node: tree_sitter::Node) -> tree_sitter::Node {
+    while matches!(node.kind(), "generic_type" | "reference_type") {
+        match node.child_by_field_name("type") {
+            Some(inner) => node = inner,
+            None => break,
+        }
+    }
+    node
+}
+
+/// The name a node contributes to the qualified path of the blocks it encloses
+///
+/// Classes, structs, traits, impls, modules, namespaces and named functions are scopes.
+/// Anonymous functions are only scopes when bound to a named variable.
+pub fn scope_name(node: &tree_sitter::Node, source: &str) -> Option<String> {
+    if let Some(symbol) = function_symbol(node, source) {
+        return symbol.name;
+    }
+
+    let name = match node.kind() {
+        "impl_item" => node.child_by_field_name("type").map(base_type),
+        "class_declaration"
+        | "class_definition"
+        | "class_specifier"
+        | "struct_specifier"
+        | "interface_declaration"
+        | "enum_declaration"
+        | "struct_item"
+        | "enum_item"
+        | "trait_item"
+        | "mod_item"
+        | "namespace_definition" => node.child_by_field_name("name"),
+        _ => None,
+    }?;
+
+    Some(source[name.start_byte()..name.end_byte()].to_string())
+}
+
 pub fn should_skip_node(node: &tree_sitter::Node, source: &str) -> bool {
     is_large_array(
         node,
@@ -93,6 +221,170 @@ mod tests {
         assert!(!should_skip_node(&array_node, source));
     }
 
+    #[test]
+    fn test_is_statement_container() {
+        let source = "int main() { int x = 42; return x; }";
+        let tree = parse_source(source);
+        let function = tree.root_node().child(0).expect("Expected a function");
+        let body = function
+            .child_by_field_name("body")
+            .expect("Expected a function body");
+
+        assert!(is_statement_container(&body));
+        assert!(!is_statement_container(&function));
+    }
+
+    fn find_symbols(language: Language, source: &str) -> Vec<Symbol> {
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let mut symbols = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            symbols.extend(function_symbol(&node, source));
+            let children: Vec<tree_sitter::Node> = node.children(&mut node.walk()).collect();
+            stack.extend(children.into_iter().rev());
+        }
+        symbols
+    }
+
+    fn symbol(name: Option<&str>, kind: &'static str) -> Symbol {
+        Symbol {
+            name: name.map(str::to_string),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_function_symbol_c() {
+        let source = "static int *lookup(int key) { return 0; }";
+        let symbols = find_symbols(unsafe { tree_sitter_c() }, source);
+        assert_eq!(symbols, vec![symbol(Some("lookup"), "function")]);
+    }
+
+    #[test]
+    fn test_function_symbol_rust() {
+        let source = r#"
+        fn free() {}
+        impl Point {
+            fn norm(&self) -> f64 { let square = |x: f64| x * x; square(self.x) }
+        }
+        "#;
+        let symbols = find_symbols(tree_sitter_rust::language(), source);
+        assert_eq!(
+            symbols,
+            vec![
+                symbol(Some("free"), "function"),
+                symbol(Some("norm"), "method"),
+                symbol(Some("square"), "closure"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_symbol_python() {
+        let source = "class Shape:\n    @property\n    def area(self):\n        return 0\n\ndef main():\n    pass\n";
+        let symbols = find_symbols(tree_sitter_python::language(), source);
+        assert_eq!(
+            symbols,
+            vec![
+                symbol(Some("area"), "method"),
+                symbol(Some("main"), "function")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_symbol_java_and_javascript() {
+        let source = "class A { A() {} void run() { Runnable r = () -> {}; } }";
+        let symbols = find_symbols(tree_sitter_java::language(), source);
+        assert_eq!(
+            symbols,
+            vec![
+                symbol(Some("A"), "constructor"),
+                symbol(Some("run"), "method"),
+                symbol(Some("r"), "closure"),
+            ]
+        );
+
+        let source = "class B { go() {} }\nconst handler = () => {};\ncall(function () {});";
+        let symbols = find_symbols(tree_sitter_javascript::language(), source);
+        assert_eq!(
+            symbols,
+            vec![
+                symbol(Some("go"), "method"),
+                symbol(Some("handler"), "closure"),
+                symbol(None, "function"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_symbol_cpp_and_go() {
+        let source = "struct S { void f() {} };\nvoid S::g() {}";
+        let symbols = find_symbols(tree_sitter_cpp::language(), source);
+        assert_eq!(
+            symbols,
+            vec![
+                symbol(Some("f"), "method"),
+                symbol(Some("S::g"), "function")
+            ]
+        );
+
+        let source = "package main\nfunc (s *S) Run() {}\nfunc main() { f := func() {}; f() }";
+        let symbols = find_symbols(tree_sitter_go::language(), source);
+        assert_eq!(
+            symbols,
+            vec![
+                symbol(Some("Run"), "method"),
+                symbol(Some("main"), "function"),
+                symbol(None, "closure"),
+            ]
+        );
+    }
+
+    fn find_scope_names(language: Language, source: &str) -> Vec<String> {
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let mut names = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            names.extend(scope_name(&node, source));
+            let children: Vec<tree_sitter::Node> = node.children(&mut node.walk()).collect();
+            stack.extend(children.into_iter().rev());
+        }
+        names
+    }
+
+    #[test]
+    fn test_scope_name() {
+        let source = "mod store { impl<T> Cache<T> { fn get(&self) { self.items.map(|x| x); } } }";
+        assert_eq!(
+            find_scope_names(tree_sitter_rust::language(), source),
+            vec!["store", "Cache", "get"]
+        );
+
+        let source = "impl<'a, T> fmt::Display for io::Wrapper<'a, T> { fn fmt() {} }\n\
+                      impl<K> Lookup for &Table<K> { fn find() {} }";
+        assert_eq!(
+            find_scope_names(tree_sitter_rust::language(), source),
+            vec!["io::Wrapper", "fmt", "Table", "find"]
+        );
+
+        let source = "class UserService { interface Hook {} void save() {} }";
+        assert_eq!(
+            find_scope_names(tree_sitter_java::language(), source),
+            vec!["UserService", "Hook", "save"]
+        );
+
+        let source = "namespace net { struct Socket { void close() {} }; }";
+        assert_eq!(
+            find_scope_names(tree_sitter_cpp::language(), source),
+            vec!["net", "Socket", "close"]
+        );
+    }
+
     #[test]
     fn test_non_array_node() {
         let source = r#"
@@ -1,9 +1,16 @@
-use crate::models::code_types::{CodeBlock, CodeBlockNode, CodeBlockRef};
-use crate::parser::ast_collection::{collect_ast_content, compute_ast_fingerprint};
-use crate::parser::ast_node::should_skip_node;
+use crate::models::code_types::{
+    CloneType, CodeBlock, CodeBlockNode, CodeBlockRef, Granularity, ParseOptions, Symbol, Token,
+};
+use crate::parser::ast_collection::{
+    collect_kind_sequence, collect_tokens, compute_subtree_digests, KindSequence, SubtreeDigest,
+};
+use crate::parser::ast_node::{
+    function_symbol, is_statement_container, scope_name, should_skip_node,
+};
 use crate::utils::language_mapping::get_language_from_extension;
 use reson::TREE_PARSING_MAX_DEPTH;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs;
 use std::rc::{Rc, Weak};
 use tree_sitter::{Language, Parser, Tree};
@@ -34,10 +41,7 @@ pub fn set_parser_language(parser: &mut Parser, language: &str) -> Result<(), St
         .map_err(|_| "Failed to set language".to_string())
 }
 
-pub fn parse_file(
-    file_path: &std::path::Path,
-    threshold: usize,
-) -> Result<(Vec<CodeBlockRef>, Tree, String), String> {
+fn parse_source(file_path: &std::path::Path) -> Result<(Tree, String), String> {
     let source_code = fs::read_to_string(file_path).map_err(|_| "Failed to read file")?;
     let mut parser = Parser::new();
 
@@ -47,47 +51,160 @@ pub fn parse_file(
         .ok_or("Unsupported file extension")?;
     let language = get_language_from_extension(extension).ok_or("Unsupported file extension")?;
 
-    set_parser_language(&mut parser, &language)?;
+    set_parser_language(&mut parser, language)?;
 
     let tree = parser
         .parse(&source_code, None)
         .ok_or("Failed to parse code")?;
-    let code_blocks = extract_code_blocks(tree.clone(), &source_code, threshold);
+
+    Ok((tree, source_code))
+}
+
+pub fn parse_file(
+    file_path: &std::path::Path,
+    options: &ParseOptions,
+) -> Result<(Vec<CodeBlockRef>, Tree, String), String> {
+    let (tree, source_code) = parse_source(file_path)?;
+    let code_blocks = extract_code_blocks(tree.clone(), &source_code, options);
 
     Ok((code_blocks, tree, source_code))
 }
 
-pub fn extract_code_blocks(tree: Tree, source: &str, threshold: usize) -> Vec<CodeBlockRef> {
+/// Parse a file into the normalized leaf tokens used by the token engine, with its tree
+/// and source
+pub fn tokenize_file(
+    file_path: &std::path::Path,
+    clone_type: CloneType,
+) -> Result<(Vec<Token>, Tree, String), String> {
+    let (tree, source_code) = parse_source(file_path)?;
+    let tokens = collect_tokens(tree.root_node(), &source_code, clone_type);
+
+    Ok((tokens, tree, source_code))
+}
+
+/// Shared, read-only state for a single `traverse_tree` walk
+struct TraversalContext<'a> {
+    source: &'a str,
+    threshold: usize,
+    granularity: Granularity,
+    max_depth: usize,
+    digests: &'a HashMap<usize, SubtreeDigest>,
+    kind_sequence: Option<&'a KindSequence>,
+    statement_sequences: bool,
+}
+
+pub fn extract_code_blocks(tree: Tree, source: &str, options: &ParseOptions) -> Vec<CodeBlockRef> {
     let mut cursor = tree.walk();
     let mut code_blocks = Vec::new();
+    let digests = compute_subtree_digests(tree.root_node(), source, options.clone_type);
+    let kind_sequence = options
+        .kind_sequences
+        .then(|| collect_kind_sequence(tree.root_node(), source, options.clone_type));
+    let context = TraversalContext {
+        source,
+        threshold: options.threshold,
+        granularity: options.granularity,
+        max_depth: TREE_PARSING_MAX_DEPTH,
+        digests: &digests,
+        kind_sequence: kind_sequence.as_ref(),
+        statement_sequences: options.statement_sequences,
+    };
 
     traverse_tree(
         &mut cursor,
-        source,
+        &context,
         &mut code_blocks,
-        threshold,
         0,
-        TREE_PARSING_MAX_DEPTH,
         None,
+        &mut Vec::new(),
     );
 
     code_blocks
 }
 
+fn build_code_block(node: &tree_sitter::Node, digest: &SubtreeDigest) -> CodeBlock {
+    let fingerprint = if digest.ast_nodes == 0 {
+        "blank_ast".to_string()
+    } else {
+        digest.fingerprint()
+    };
+
+    CodeBlock {
+        start_byte: node.start_byte(),
+        end_byte: node.end_byte(),
+        start_line: node.start_position().row + 1,
+        end_line: node.end_position().row + 1,
+        fingerprint,
+        ast_lines: digest.ast_nodes,
+    }
+}
+
+/// The fingerprinted statements directly inside a block-like node
+fn collect_statements(node: &tree_sitter::Node, context: &TraversalContext) -> Vec<CodeBlock> {
+    if !context.statement_sequences || !is_statement_container(node) {
+        return Vec::new();
+    }
+
+    node.named_children(&mut node.walk())
+        .filter(|child| !child.kind().contains("comment"))
+        .map(|child| build_code_block(&child, &context.digests[&child.id()]))
+        .collect()
+}
+
+fn build_block_node(
+    node: &tree_sitter::Node,
+    context: &TraversalContext,
+    parent: Option<Weak<RefCell<CodeBlockNode>>>,
+    symbol: Option<Symbol>,
+    scope: &[String],
+) -> CodeBlockRef {
+    let start_line = node.start_position().row + 1;
+    let end_line = node.end_position().row + 1;
+    let digest = context.digests[&node.id()];
+    if digest.ast_nodes == 0 {
+        log::debug!(
+            "No AST representation found for node at lines {}-{}",
+            start_line,
+            end_line
+        );
+    } else {
+        log::debug!(
+            "Using subtree fingerprint for node at lines {}-{}, AST lines: {}",
+            start_line,
+            end_line,
+            digest.ast_nodes
+        );
+    }
+    let code_block = build_code_block(node, &digest);
+
+    let kind_sequence = context
+        .kind_sequence
+        .map(|sequence| sequence.subtree(node, &digest).to_vec())
+        .unwrap_or_default();
+
+    Rc::new(RefCell::new(CodeBlockNode {
+        code_block,
+        parent,
+        kind_sequence,
+        statements: collect_statements(node, context),
+        symbol,
+        context: (!scope.is_empty()).then(|| scope.join("::")),
+    }))
+}
+
 fn should_return_due_to_depth(depth: usize, max_depth: usize) -> bool {
     depth > max_depth
 }
 
 fn traverse_tree(
     cursor: &mut tree_sitter::TreeCursor,
-    source: &str,
+    context: &TraversalContext,
     code_blocks: &mut Vec<CodeBlockRef>,
-    threshold: usize,
     depth: usize,
-    max_depth: usize,
     parent: Option<Weak<RefCell<CodeBlockNode>>>,
+    scope: &mut Vec<String>,
 ) {
-    if should_return_due_to_depth(depth, max_depth) {
+    if should_return_due_to_depth(depth, context.max_depth) {
         return;
     }
 
@@ -98,8 +215,8 @@ fn traverse_tree(
             let end_line = node.end_position().row + 1;
             let line_count = end_line - start_line + 1;
 
-            if line_count >= threshold {
-                if should_skip_node(&node, source) {
+            if line_count >= context.threshold {
+                if should_skip_node(&node, context.source) {
                     log::debug!("Skipping node at lines {}-{}", start_line, end_line);
                     if !cursor.goto_next_sibling() {
                         break;
@@ -107,52 +224,33 @@ fn traverse_tree(
                     continue;
                 }
 
-                let (ast_representation, ast_lines) = collect_ast_content(node, source);
-                let fingerprint = if ast_representation.is_empty() {
-                    log::debug!(
-                        "No AST representation found for node at lines {}-{}",
-                        start_line,
-                        end_line
-                    );
-                    "blank_ast".to_string()
+                // A named scope encloses itself and everything below it
+                let scope_name = scope_name(&node, context.source);
+                let is_scope = scope_name.is_some();
+                scope.extend(scope_name);
+
+                let symbol = function_symbol(&node, context.source);
+                // In function mode other nodes are only walked through, not recorded
+                let node_ref = if context.granularity == Granularity::Block || symbol.is_some() {
+                    let node_ref = build_block_node(&node, context, parent.clone(), symbol, scope);
+                    code_blocks.push(node_ref.clone());
+                    Some(node_ref)
                 } else {
-                    log::debug!(
-                        "Computing fingerprint for node at lines {}-{}, AST lines: {}",
-                        start_line,
-                        end_line,
-                        ast_lines
-                    );
-                    compute_ast_fingerprint(&ast_representation)
-                };
-
-                let code_block = CodeBlock {
-                    start_byte: node.start_byte(),
-                    end_byte: node.end_byte(),
-                    start_line,
-                    end_line,
-                    fingerprint,
-                    ast_lines,
+                    None
                 };
 
-                let node_ref = Rc::new(RefCell::new(CodeBlockNode {
-                    code_block,
-                    parent: parent.clone(),
-                }));
-
-                code_blocks.push(node_ref.clone());
-
                 if cursor.goto_first_child() {
-                    traverse_tree(
-                        cursor,
-                        source,
-                        code_blocks,
-                        threshold,
-                        depth + 1,
-                        max_depth,
-                        Some(Rc::downgrade(&node_ref)),
-                    );
+                    let block_parent = match &node_ref {
+                        Some(node_ref) => Some(Rc::downgrade(node_ref)),
+                        None => parent.clone(),
+                    };
+                    traverse_tree(cursor, context, code_blocks, depth + 1, block_parent, scope);
                     cursor.goto_parent();
                 }
+
+                if is_scope {
+                    scope.pop();
+                }
             }
         }
 
@@ -167,7 +265,15 @@ mod tests {
     use super::*;
     use crate::utils::files::{create_temp_file, delete_temp_file};
 
+    fn parse_options(threshold: usize) -> ParseOptions {
+        ParseOptions {
+            threshold,
+            ..Default::default()
+        }
+    }
+
     #[cfg(test)]
+    #[allow(clippy::module_inception)]
     mod tests {
         use super::*;
         use tree_sitter::Parser;
@@ -220,14 +326,23 @@ mod tests {
         let depth = TREE_PARSING_MAX_DEPTH + 1;
 
         // Call traverse_tree
+        let digests = compute_subtree_digests(tree.root_node(), source, Default::default());
+        let context = TraversalContext {
+            source,
+            threshold: 1,
+            granularity: Granularity::Block,
+            max_depth: TREE_PARSING_MAX_DEPTH,
+            digests: &digests,
+            kind_sequence: None,
+            statement_sequences: false,
+        };
         traverse_tree(
             &mut cursor,
-            source,
+            &context,
             &mut code_blocks,
-            1,
             depth,
-            TREE_PARSING_MAX_DEPTH,
             None,
+            &mut Vec::new(),
         );
 
         // Assert that no code blocks were added
@@ -251,14 +366,23 @@ mod tests {
         let mut cursor = tree.walk();
         assert!(cursor.goto_first_child(), "Expected top-level child nodes");
         let mut code_blocks: Vec<CodeBlockRef> = Vec::new();
+        let digests = compute_subtree_digests(tree.root_node(), &source, Default::default());
+        let context = TraversalContext {
+            source: &source,
+            threshold: 1,
+            granularity: Granularity::Block,
+            max_depth: TREE_PARSING_MAX_DEPTH,
+            digests: &digests,
+            kind_sequence: None,
+            statement_sequences: false,
+        };
         traverse_tree(
             &mut cursor,
-            &source,
+            &context,
             &mut code_blocks,
-            1,
             0,
-            TREE_PARSING_MAX_DEPTH,
             None,
+            &mut Vec::new(),
         );
         let has_second_line_block = code_blocks.iter().any(|block| {
             let block = block.borrow();
@@ -271,6 +395,166 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_file_records_kind_sequences() {
+        let content = r#"
+        int main() {
+            int a = 0;
+            int b = 1;
+            return a + b;
+        }
+        "#;
+        let file_path = create_temp_file(content, "c");
+
+        let (code_blocks, _tree, _source_code) = parse_file(
+            &file_path,
+            &ParseOptions {
+                kind_sequences: true,
+                ..parse_options(3)
+            },
+        )
+        .unwrap();
+        assert!(!code_blocks.is_empty());
+        for block in &code_blocks {
+            let block = block.borrow();
+            assert_eq!(block.kind_sequence.len(), block.code_block.ast_lines);
+        }
+
+        let (code_blocks, _tree, _source_code) = parse_file(&file_path, &parse_options(3)).unwrap();
+        assert!(code_blocks
+            .iter()
+            .all(|block| block.borrow().kind_sequence.is_empty()));
+
+        delete_temp_file(&file_path);
+    }
+
+    #[test]
+    fn test_parse_file_records_statements() {
+        let content = r#"
+        int main() {
+            int a = 0;
+            // Comments are not statements
+            int b = 1;
+            return a + b;
+        }
+        "#;
+        let file_path = create_temp_file(content, "c");
+
+        let (code_blocks, _tree, _source_code) = parse_file(
+            &file_path,
+            &ParseOptions {
+                statement_sequences: true,
+                ..parse_options(3)
+            },
+        )
+        .unwrap();
+        let statements: Vec<Vec<usize>> = code_blocks
+            .iter()
+            .map(|block| {
+                let block = block.borrow();
+                block
+                    .statements
+                    .iter()
+                    .map(|statement| statement.start_line)
+                    .collect()
+            })
+            .filter(|lines: &Vec<usize>| !lines.is_empty())
+            .collect();
+        assert_eq!(statements, vec![vec![3, 5, 6]]);
+
+        delete_temp_file(&file_path);
+    }
+
+    #[test]
+    fn test_parse_file_function_granularity() {
+        let content = r#"
+        struct Point {
+            int x;
+            int y;
+        };
+
+        int area(int width, int height) {
+            int result = width * height;
+            return result;
+        }
+        "#;
+        let file_path = create_temp_file(content, "c");
+
+        let (code_blocks, _tree, _source_code) = parse_file(
+            &file_path,
+            &ParseOptions {
+                granularity: Granularity::Function,
+                ..parse_options(3)
+            },
+        )
+        .unwrap();
+        let symbols: Vec<Symbol> = code_blocks
+            .iter()
+            .filter_map(|block| block.borrow().symbol.clone())
+            .collect();
+        assert_eq!(code_blocks.len(), 1);
+        assert_eq!(
+            symbols,
+            vec![Symbol {
+                name: Some("area".to_string()),
+                kind: "function",
+            }]
+        );
+        assert!(code_blocks[0].borrow().parent.is_none());
+
+        delete_temp_file(&file_path);
+    }
+
+    #[test]
+    fn test_parse_file_records_context() {
+        let content = r#"
+class UserService {
+    void save(User user) {
+        if (user.isValid()) {
+            repository.store(user);
+            log("saved");
+        }
+    }
+}
+"#;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("UserService.java");
+        std::fs::write(&file_path, content).unwrap();
+
+        let (code_blocks, _tree, _source_code) = parse_file(&file_path, &parse_options(3)).unwrap();
+        let contexts: Vec<(usize, Option<String>)> = code_blocks
+            .iter()
+            .map(|block| {
+                let block = block.borrow();
+                (block.code_block.start_line, block.context.clone())
+            })
+            .collect();
+        assert!(contexts.contains(&(2, Some("UserService".to_string()))));
+        assert!(contexts.contains(&(3, Some("UserService::save".to_string()))));
+        assert!(contexts.contains(&(4, Some("UserService::save".to_string()))));
+        assert!(contexts.iter().any(|(_, context)| context.is_none()));
+    }
+
+    #[test]
+    fn test_tokenize_file_survives_syntax_errors() {
+        let content = r#"
+        int main() {
+            int a = 0;
+            int b = a +* 1;
+            return a + b;
+        }
+        "#;
+        let file_path = create_temp_file(content, "c");
+
+        let (tokens, _, source) = tokenize_file(&file_path, CloneType::Parameterized).unwrap();
+        assert_eq!(source, content);
+        assert_eq!(tokens.first().map(|token| token.start_line), Some(2));
+        assert_eq!(tokens.last().map(|token| token.end_line), Some(6));
+        assert!(tokens.iter().any(|token| token.start_line == 4));
+
+        delete_temp_file(&file_path);
+    }
+
     #[test]
     fn test_parse_c_file() {
         let content = r#"
@@ -287,15 +571,15 @@ mod tests {
         "#;
         let file_path = create_temp_file(content, "c");
 
-        let result = parse_file(&file_path, 5);
+        let result = parse_file(&file_path, &parse_options(5));
 
         assert!(result.is_ok(), "Parsing C file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
 
         assert_eq!(source_code, content);
-        assert!(code_blocks.len() > 0);
+        assert!(!code_blocks.is_empty());
 
-        let result = parse_file(&file_path, 20);
+        let result = parse_file(&file_path, &parse_options(20));
 
         assert!(result.is_ok(), "Parsing C file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
@@ -322,15 +606,15 @@ mod tests {
         "#;
         let file_path = create_temp_file(content, "cpp");
 
-        let result = parse_file(&file_path, 5);
+        let result = parse_file(&file_path, &parse_options(5));
 
         assert!(result.is_ok(), "Parsing C++ file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
 
         assert_eq!(source_code, content);
-        assert!(code_blocks.len() > 0);
+        assert!(!code_blocks.is_empty());
 
-        let result = parse_file(&file_path, 20);
+        let result = parse_file(&file_path, &parse_options(20));
 
         assert!(result.is_ok(), "Parsing C++ file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
@@ -358,15 +642,15 @@ mod tests {
         "#;
         let file_path = create_temp_file(content, "java");
 
-        let result = parse_file(&file_path, 5);
+        let result = parse_file(&file_path, &parse_options(5));
 
         assert!(result.is_ok(), "Parsing Java file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
 
         assert_eq!(source_code, content);
-        assert!(code_blocks.len() > 0);
+        assert!(!code_blocks.is_empty());
 
-        let result = parse_file(&file_path, 20);
+        let result = parse_file(&file_path, &parse_options(20));
 
         assert!(result.is_ok(), "Parsing Java file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
@@ -391,15 +675,15 @@ mod tests {
         "#;
         let file_path = create_temp_file(content, "py");
 
-        let result = parse_file(&file_path, 5);
+        let result = parse_file(&file_path, &parse_options(5));
 
         assert!(result.is_ok(), "Parsing Python file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
 
         assert_eq!(source_code, content);
-        assert!(code_blocks.len() > 0);
+        assert!(!code_blocks.is_empty());
 
-        let result = parse_file(&file_path, 20);
+        let result = parse_file(&file_path, &parse_options(20));
 
         assert!(result.is_ok(), "Parsing Python file failed");
         let (code_blocks, _tree, source_code) = result.unwrap();
@@ -415,7 +699,7 @@ mod tests {
         let content = "unsupported content";
         let file_path = create_temp_file(content, "txt");
 
-        let result = parse_file(&file_path, 5);
+        let result = parse_file(&file_path, &parse_options(5));
 
         assert!(result.is_err(), "Parsing unsupported file should fail");
         assert_eq!(result.err().unwrap(), "Unsupported file extension");
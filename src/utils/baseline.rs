@@ -0,0 +1,292 @@
+use crate::models::detection_types::{BaselineStatus, DuplicateReport};
+use crate::models::report::{Report, ReportOptions};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+/// Load a report written by an earlier run with `--output-format json`
+pub fn load_baseline(path: &Path) -> Result<Report, std::io::Error> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        std::io::Error::new(
+            e.kind(),
+            format!("Cannot read baseline {}: {}", path.display(), e),
+        )
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} is not a reson JSON report: {}", path.display(), e),
+        )
+    })
+}
+
+/// Fail when the baseline was created with options that change fingerprints, since
+/// every class would then count as new
+///
+/// Reports without metadata do not record their options and are accepted as they are.
+pub fn check_baseline_options(
+    baseline: &Report,
+    options: &ReportOptions,
+) -> Result<(), std::io::Error> {
+    if baseline.metadata.tool_version.is_empty() {
+        return Ok(());
+    }
+    let previous = &baseline.metadata.options;
+    let mismatched: Vec<&str> = [
+        ("--clone-type", previous.clone_type == options.clone_type),
+        ("--engine", previous.engine == options.engine),
+        ("--granularity", previous.granularity == options.granularity),
+        (
+            "--statement-sequences",
+            previous.statement_sequences == options.statement_sequences,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, same)| !same)
+    .map(|(option, _)| option)
+    .collect();
+    if mismatched.is_empty() {
+        return Ok(());
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!(
+            "The baseline was created with a different {}; create it again with the current options",
+            mismatched.join(", ")
+        ),
+    ))
+}
+
+/// A file path relative to the source path of its report, with `/` separators, so that
+/// runs started as `src`, `./src` or from another checkout directory agree
+fn normalize_path(path: &str, source_path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    let root = source_path.replace('\\', "/");
+    let root = root.trim_start_matches("./").trim_end_matches('/');
+    match path.strip_prefix(root) {
+        Some(relative) if !root.is_empty() && relative.starts_with('/') => {
+            relative.trim_start_matches('/').to_string()
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Identity of a clone class across runs: its fingerprint and the files of its blocks
+///
+/// Line numbers are left out so that classes survive edits above them.
+fn class_key(report: &DuplicateReport, source_path: &str) -> (String, Vec<String>) {
+    let mut files: Vec<String> = report
+        .blocks
+        .iter()
+        .map(|block| normalize_path(&block.source_file, source_path))
+        .collect();
+    files.sort();
+    (report.fingerprint.clone(), files)
+}
+
+/// Mark each clone class as new or existing, and return the baseline classes that were
+/// not found again, marked as resolved
+///
+/// Classes with the same key are paired in report order, so that when a class is
+/// duplicated once more only the extra one counts as new.
+pub fn compare_with_baseline(
+    records: &mut [DuplicateReport],
+    source_path: &str,
+    baseline: &Report,
+) -> Vec<DuplicateReport> {
+    let baseline_root = &baseline.metadata.options.source_path;
+    let mut unmatched: HashMap<(String, Vec<String>), VecDeque<usize>> = HashMap::new();
+    for (index, report) in baseline.records.iter().enumerate() {
+        unmatched
+            .entry(class_key(report, baseline_root))
+            .or_default()
+            .push_back(index);
+    }
+
+    for report in records.iter_mut() {
+        let matched = unmatched
+            .get_mut(&class_key(report, source_path))
+            .and_then(VecDeque::pop_front);
+        report.baseline_status = Some(match matched {
+            Some(_) => BaselineStatus::Existing,
+            None => BaselineStatus::New,
+        });
+    }
+
+    // Keep the baseline order for the classes that are gone
+    let remaining: BTreeSet<usize> = unmatched.into_values().flatten().collect();
+    remaining
+        .into_iter()
+        .map(|index| DuplicateReport {
+            baseline_status: Some(BaselineStatus::Resolved),
+            ..baseline.records[index].clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+    use crate::models::detection_types::DuplicateBlock;
+    use crate::models::report::{Metadata, ReportOptions};
+    use crate::utils::files::{create_temp_file, delete_temp_file};
+
+    fn report(fingerprint: &str, blocks: &[(&str, usize)]) -> DuplicateReport {
+        DuplicateReport {
+            fingerprint: fingerprint.to_string(),
+            clone_type: CloneType::Parameterized,
+            line_count: 6,
+            similarity: None,
+            baseline_status: None,
+            blocks: blocks
+                .iter()
+                .map(|(file, start)| DuplicateBlock {
+                    start_line_number: *start,
+                    end_line_number: start + 5,
+                    source_file: file.to_string(),
+                    symbol_name: None,
+                    symbol_kind: None,
+                    context: None,
+                    snippet: None,
+                    byte_range: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn baseline(source_path: &str, records: Vec<DuplicateReport>) -> Report {
+        let options = ReportOptions {
+            source_path: source_path.to_string(),
+            ..ReportOptions::default()
+        };
+        Report::new(records, &[], Metadata::new(options))
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("./src/a.c", "./src"), "a.c");
+        assert_eq!(normalize_path("src/lib/a.c", "src"), "lib/a.c");
+        assert_eq!(normalize_path("src\\lib\\a.c", "src/"), "lib/a.c");
+        assert_eq!(normalize_path("./a.c", "."), "a.c");
+        assert_eq!(normalize_path("srcs/a.c", "src"), "srcs/a.c");
+        assert_eq!(normalize_path("src/a.c", ""), "src/a.c");
+    }
+
+    #[test]
+    fn test_compare_with_baseline() {
+        let baseline = baseline(
+            "./project",
+            vec![
+                report("aa", &[("./project/a.c", 10), ("./project/b.c", 40)]),
+                report("bb", &[("./project/a.c", 70), ("./project/c.c", 5)]),
+            ],
+        );
+        // The first class moved down by three lines, the second is gone
+        let mut records = vec![
+            report("aa", &[("project/b.c", 40), ("project/a.c", 13)]),
+            report("cc", &[("project/a.c", 90), ("project/d.c", 1)]),
+        ];
+
+        let resolved = compare_with_baseline(&mut records, "project", &baseline);
+        assert_eq!(records[0].baseline_status, Some(BaselineStatus::Existing));
+        assert_eq!(records[1].baseline_status, Some(BaselineStatus::New));
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].fingerprint, "bb");
+        assert_eq!(resolved[0].baseline_status, Some(BaselineStatus::Resolved));
+    }
+
+    #[test]
+    fn test_compare_with_baseline_counts_repeated_classes() {
+        let baseline = baseline("src", vec![report("aa", &[("src/a.c", 1), ("src/b.c", 1)])]);
+        let mut records = vec![
+            report("aa", &[("src/a.c", 1), ("src/b.c", 1)]),
+            report("aa", &[("src/a.c", 30), ("src/b.c", 30)]),
+            // Same fingerprint, but now spanning another file
+            report("aa", &[("src/a.c", 60), ("src/c.c", 1)]),
+        ];
+
+        let resolved = compare_with_baseline(&mut records, "src", &baseline);
+        let statuses: Vec<_> = records.iter().map(|r| r.baseline_status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Some(BaselineStatus::Existing),
+                Some(BaselineStatus::New),
+                Some(BaselineStatus::New)
+            ]
+        );
+        assert!(resolved.is_empty());
+    }
+
+    #[test]
+    fn test_load_baseline() {
+        let report = baseline("src", vec![report("aa", &[("src/a.c", 1), ("src/b.c", 1)])]);
+        let file_path = create_temp_file(&serde_json::to_string(&report).unwrap(), "json");
+        assert_eq!(load_baseline(&file_path).unwrap(), report);
+        delete_temp_file(&file_path);
+
+        let file_path = create_temp_file("<duplication/>", "xml");
+        let error = load_baseline(&file_path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("is not a reson JSON report"));
+        delete_temp_file(&file_path);
+
+        let error = load_baseline(Path::new("missing/baseline.json")).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_load_baseline_in_first_release_format() {
+        // Clone classes without clone types, symbols or snippets, and no metadata
+        let content = r#"{
+  "summary": { "total_lines": 12, "duplicated_lines": 12, "duplication_ratio": 1.0 },
+  "records": [
+    {
+      "fingerprint": "aa",
+      "line_count": 6,
+      "blocks": [
+        { "start_line_number": 1, "end_line_number": 6, "source_file": "src/a.c" },
+        { "start_line_number": 1, "end_line_number": 6, "source_file": "src/b.c" }
+      ]
+    }
+  ]
+}"#;
+        let file_path = create_temp_file(content, "json");
+        let loaded = load_baseline(&file_path).unwrap();
+        delete_temp_file(&file_path);
+        assert_eq!(
+            loaded.records,
+            vec![report("aa", &[("src/a.c", 1), ("src/b.c", 1)])]
+        );
+
+        let options = ReportOptions {
+            clone_type: CloneType::Exact,
+            ..ReportOptions::default()
+        };
+        assert!(check_baseline_options(&loaded, &options).is_ok());
+        let mut records = vec![report("aa", &[("src/a.c", 3), ("src/b.c", 3)])];
+        assert!(compare_with_baseline(&mut records, "", &loaded).is_empty());
+        assert_eq!(records[0].baseline_status, Some(BaselineStatus::Existing));
+    }
+
+    #[test]
+    fn test_check_baseline_options() {
+        let baseline = baseline("src", vec![]);
+        let mut options = ReportOptions {
+            source_path: "other".to_string(),
+            threshold: 8,
+            ..ReportOptions::default()
+        };
+        assert!(check_baseline_options(&baseline, &options).is_ok());
+
+        options.clone_type = CloneType::Exact;
+        options.statement_sequences = true;
+        let error = check_baseline_options(&baseline, &options).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(error
+            .to_string()
+            .contains("different --clone-type, --statement-sequences"));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::models::code_types::{CloneType, Engine, Granularity};
+use crate::utils::filters::{is_excluded, FileSelection};
+use crate::utils::language_mapping::{get_language_from_extension, get_language_mapping};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file looked up from the source path upward
+pub const CONFIG_FILE_NAME: &str = "reson.toml";
+
+/// Settings that can differ per directory or per language
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverrideSettings {
+    pub threshold: Option<usize>,
+    pub min_ast_nodes: Option<usize>,
+    pub excludes: Option<Vec<String>>,
+}
+
+impl OverrideSettings {
+    fn is_empty(&self) -> bool {
+        self == &OverrideSettings::default()
+    }
+}
+
+/// The `[overrides.directories.*]` and `[overrides.languages.*]` tables
+///
+/// Directories are relative to the configuration file.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overrides {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub directories: BTreeMap<String, OverrideSettings>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub languages: BTreeMap<String, OverrideSettings>,
+}
+
+impl Overrides {
+    pub fn is_empty(&self) -> bool {
+        self.directories.is_empty() && self.languages.is_empty()
+    }
+
+    /// Drop a setting from every override, because the command line set it for all files
+    pub fn clear(&mut self, clear: impl Fn(&mut OverrideSettings)) {
+        for settings in self
+            .directories
+            .values_mut()
+            .chain(self.languages.values_mut())
+        {
+            clear(settings);
+        }
+        self.directories.retain(|_, settings| !settings.is_empty());
+        self.languages.retain(|_, settings| !settings.is_empty());
+    }
+}
+
+/// Contents of a `reson.toml`; every key mirrors the command-line option of the same name
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub source_path: Option<PathBuf>,
+    pub languages: Option<Vec<String>>,
+    pub excludes: Option<Vec<String>>,
+    pub output_format: Option<String>,
+    pub output_file: Option<PathBuf>,
+    pub threshold: Option<usize>,
+    pub min_ast_nodes: Option<usize>,
+    pub clone_type: Option<CloneType>,
+    pub granularity: Option<Granularity>,
+    pub similarity: Option<f64>,
+    pub lsh: Option<bool>,
+    pub lsh_bands: Option<usize>,
+    pub lsh_rows: Option<usize>,
+    pub engine: Option<Engine>,
+    pub min_tokens: Option<usize>,
+    pub statement_sequences: Option<bool>,
+    pub include_snippets: Option<bool>,
+    pub snippet_max_lines: Option<usize>,
+    pub threads: Option<usize>,
+    pub baseline: Option<PathBuf>,
+    pub only_new: Option<bool>,
+    pub fail_on_duplicate_lines: Option<usize>,
+    pub fail_on_percentage: Option<f64>,
+    pub fail_on_new: Option<bool>,
+    pub max_file_size: Option<u64>,
+    pub no_ignore: Option<bool>,
+    pub debug: Option<bool>,
+    #[serde(skip_serializing_if = "Overrides::is_empty")]
+    pub overrides: Overrides,
+}
+
+/// Read a configuration file; relative paths in it are taken from its directory
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read config {}: {}", path.display(), e))?;
+    let mut config: Config =
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let language_mapping = get_language_mapping();
+    if let Some(language) = config
+        .overrides
+        .languages
+        .keys()
+        .find(|language| !language_mapping.contains_key(language.as_str()))
+    {
+        return Err(format!(
+            "{}: unknown language '{}' in [overrides.languages]",
+            path.display(),
+            language
+        ));
+    }
+
+    let override_excludes = config
+        .overrides
+        .directories
+        .values()
+        .chain(config.overrides.languages.values())
+        .filter_map(|settings| settings.excludes.as_ref());
+    for pattern in config.excludes.iter().chain(override_excludes).flatten() {
+        if let Err(e) = glob::Pattern::new(pattern) {
+            return Err(format!(
+                "{}: invalid exclude pattern '{}': {}",
+                path.display(),
+                pattern,
+                e
+            ));
+        }
+    }
+
+    let base = path.parent().unwrap_or(Path::new(""));
+    let resolve = |path: PathBuf| {
+        if path.is_absolute() {
+            path
+        } else {
+            base.join(path)
+        }
+    };
+    config.source_path = config.source_path.map(resolve);
+    config.baseline = config.baseline.map(resolve);
+    // `-` stands for stdout, not a file
+    config.output_file = config.output_file.map(|file| {
+        if file == Path::new("-") {
+            file
+        } else {
+            resolve(file)
+        }
+    });
+    Ok(config)
+}
+
+/// The nearest `reson.toml` in the source path or one of its parent directories
+pub fn find_config(source_path: &Path) -> Option<PathBuf> {
+    let source_path = fs::canonicalize(source_path).ok()?;
+    let start = if source_path.is_file() {
+        source_path.parent()?
+    } else {
+        &source_path
+    };
+    start
+        .ancestors()
+        .map(|directory| directory.join(CONFIG_FILE_NAME))
+        .find(|config| config.is_file())
+}
+
+/// Threshold and AST-node floor of each selected file, after the overrides
+#[derive(Debug, Default)]
+pub struct FileSettings {
+    threshold: usize,
+    min_ast_nodes: usize,
+    files: HashMap<String, (usize, usize)>,
+}
+
+impl FileSettings {
+    pub fn new(threshold: usize, min_ast_nodes: usize) -> Self {
+        FileSettings {
+            threshold,
+            min_ast_nodes,
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn threshold(&self, file: &str) -> usize {
+        self.files.get(file).map_or(self.threshold, |file| file.0)
+    }
+
+    pub fn min_ast_nodes(&self, file: &str) -> usize {
+        self.files
+            .get(file)
+            .map_or(self.min_ast_nodes, |file| file.1)
+    }
+}
+
+/// Merge the overrides that apply to one file
+///
+/// A directory override wins over a language override and a deeper directory over its
+/// parents, while the excludes of every matching override add up.
+fn merged_settings(
+    file: &Path,
+    directories: &[(PathBuf, &OverrideSettings)],
+    languages: &BTreeMap<String, OverrideSettings>,
+) -> OverrideSettings {
+    let absolute = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    let language = file
+        .extension()
+        .and_then(|extension| get_language_from_extension(&extension.to_string_lossy()))
+        .and_then(|language| languages.get(language));
+    let matching = language.into_iter().chain(
+        directories
+            .iter()
+            .filter(|(directory, _)| absolute.starts_with(directory))
+            .map(|(_, settings)| *settings),
+    );
+
+    let mut merged = OverrideSettings::default();
+    for settings in matching {
+        merged.threshold = settings.threshold.or(merged.threshold);
+        merged.min_ast_nodes = settings.min_ast_nodes.or(merged.min_ast_nodes);
+        if let Some(excludes) = &settings.excludes {
+            merged
+                .excludes
+                .get_or_insert_with(Vec::new)
+                .extend(excludes.iter().cloned());
+        }
+    }
+    merged
+}
+
+/// Drop the files that an override excludes and work out the settings of the others
+///
+/// `config_dir` is the directory the override paths are relative to.
+pub fn apply_overrides(
+    overrides: &Overrides,
+    config_dir: &Path,
+    selection: &mut FileSelection,
+    defaults: FileSettings,
+) -> FileSettings {
+    if overrides.is_empty() {
+        return defaults;
+    }
+
+    let mut directories: Vec<(PathBuf, &OverrideSettings)> = overrides
+        .directories
+        .iter()
+        .map(|(directory, settings)| {
+            let directory = config_dir.join(directory);
+            (fs::canonicalize(&directory).unwrap_or(directory), settings)
+        })
+        .collect();
+    // Parents first, so that deeper directories are merged last and win
+    directories.sort_by_key(|(directory, _)| directory.components().count());
+
+    let mut settings = defaults;
+    let mut excluded: HashSet<PathBuf> = HashSet::new();
+    for file in &selection.files {
+        let merged = merged_settings(file, &directories, &overrides.languages);
+        if is_excluded(file, merged.excludes.as_deref().unwrap_or_default()) {
+            excluded.insert(file.clone());
+            continue;
+        }
+        settings.files.insert(
+            file.to_string_lossy().to_string(),
+            (
+                merged.threshold.unwrap_or(settings.threshold),
+                merged.min_ast_nodes.unwrap_or(settings.min_ast_nodes),
+            ),
+        );
+    }
+    selection.files.retain(|file| !excluded.contains(file));
+    selection.skipped.retain(|skipped| {
+        let file = Path::new(&skipped.path);
+        let merged = merged_settings(file, &directories, &overrides.languages);
+        !is_excluded(file, merged.excludes.as_deref().unwrap_or_default())
+    });
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, path: &str, content: &str) -> PathBuf {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_config() {
+        let dir = TempDir::new().unwrap();
+        let path = write(
+            dir.path(),
+            "reson.toml",
+            r#"
+source_path = "src"
+languages = ["rust", "c"]
+threshold = 8
+clone_type = "exact"
+output_file = "-"
+baseline = "/tmp/baseline.json"
+
+[overrides.directories."src/generated"]
+threshold = 20
+excludes = ["*.pb.rs"]
+
+[overrides.languages.c]
+min_ast_nodes = 15
+"#,
+        );
+
+        let config = load_config(&path).unwrap();
+        assert_eq!(config.source_path, Some(dir.path().join("src")));
+        assert_eq!(
+            config.languages,
+            Some(vec!["rust".to_string(), "c".to_string()])
+        );
+        assert_eq!(config.threshold, Some(8));
+        assert_eq!(config.clone_type, Some(CloneType::Exact));
+        assert_eq!(config.output_file, Some(PathBuf::from("-")));
+        assert_eq!(config.baseline, Some(PathBuf::from("/tmp/baseline.json")));
+        assert_eq!(config.min_ast_nodes, None);
+        assert_eq!(
+            config.overrides.directories["src/generated"],
+            OverrideSettings {
+                threshold: Some(20),
+                min_ast_nodes: None,
+                excludes: Some(vec!["*.pb.rs".to_string()]),
+            }
+        );
+        assert_eq!(config.overrides.languages["c"].min_ast_nodes, Some(15));
+    }
+
+    #[test]
+    fn test_load_config_invalid() {
+        let dir = TempDir::new().unwrap();
+
+        let path = write(dir.path(), "unknown.toml", "treshold = 8\n");
+        assert!(load_config(&path).unwrap_err().contains("unknown field"));
+
+        let path = write(
+            dir.path(),
+            "language.toml",
+            "[overrides.languages.cobol]\nthreshold = 8\n",
+        );
+        assert!(load_config(&path)
+            .unwrap_err()
+            .contains("unknown language 'cobol'"));
+
+        let path = write(dir.path(), "type.toml", "threshold = \"eight\"\n");
+        assert!(load_config(&path).is_err());
+
+        let path = write(dir.path(), "glob.toml", "excludes = [\"src/[gen\"]\n");
+        assert!(load_config(&path)
+            .unwrap_err()
+            .contains("invalid exclude pattern 'src/[gen'"));
+
+        let path = write(
+            dir.path(),
+            "override-glob.toml",
+            "[overrides.directories.src]\nexcludes = [\"***\"]\n",
+        );
+        assert!(load_config(&path)
+            .unwrap_err()
+            .contains("invalid exclude pattern '***'"));
+
+        let error = load_config(&dir.path().join("missing.toml")).unwrap_err();
+        assert!(error.starts_with("Cannot read config"));
+    }
+
+    #[test]
+    fn test_find_config() {
+        let dir = TempDir::new().unwrap();
+        let config = write(dir.path(), "reson.toml", "");
+        let file = write(dir.path(), "src/lib/a.rs", "fn main() {}");
+
+        let expected = fs::canonicalize(config).unwrap();
+        assert_eq!(
+            find_config(&dir.path().join("src/lib")),
+            Some(expected.clone())
+        );
+        assert_eq!(find_config(&file), Some(expected));
+        assert_eq!(find_config(&dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_overrides_clear() {
+        let mut overrides = Overrides::default();
+        overrides.directories.insert(
+            "gen".to_string(),
+            OverrideSettings {
+                threshold: Some(20),
+                ..OverrideSettings::default()
+            },
+        );
+        overrides.languages.insert(
+            "c".to_string(),
+            OverrideSettings {
+                threshold: Some(3),
+                min_ast_nodes: Some(15),
+                excludes: None,
+            },
+        );
+
+        overrides.clear(|settings| settings.threshold = None);
+        assert!(overrides.directories.is_empty());
+        assert_eq!(overrides.languages["c"].threshold, None);
+        assert_eq!(overrides.languages["c"].min_ast_nodes, Some(15));
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let dir = TempDir::new().unwrap();
+        let plain = write(dir.path(), "src/a.rs", "");
+        let header = write(dir.path(), "src/a.c", "");
+        let generated = write(dir.path(), "src/gen/b.c", "");
+        let deeper = write(dir.path(), "src/gen/deep/c.rs", "");
+        let message = write(dir.path(), "src/gen/d.pb.rs", "");
+
+        let config: Config = toml::from_str(
+            r#"
+[overrides.directories."src/gen"]
+threshold = 20
+excludes = ["*.pb.rs"]
+
+[overrides.directories."src/gen/deep"]
+threshold = 30
+
+[overrides.languages.c]
+threshold = 3
+min_ast_nodes = 15
+"#,
+        )
+        .unwrap();
+
+        let mut selection = FileSelection {
+            files: vec![
+                plain.clone(),
+                header.clone(),
+                generated.clone(),
+                deeper.clone(),
+                message,
+            ],
+            skipped: Vec::new(),
+        };
+        let settings = apply_overrides(
+            &config.overrides,
+            dir.path(),
+            &mut selection,
+            FileSettings::new(5, 10),
+        );
+
+        assert_eq!(
+            selection.files,
+            vec![
+                plain.clone(),
+                header.clone(),
+                generated.clone(),
+                deeper.clone()
+            ]
+        );
+        let file = |path: &PathBuf| path.to_string_lossy().to_string();
+        assert_eq!(settings.threshold(&file(&plain)), 5);
+        assert_eq!(settings.min_ast_nodes(&file(&plain)), 10);
+        assert_eq!(settings.threshold(&file(&header)), 3);
+        assert_eq!(settings.min_ast_nodes(&file(&header)), 15);
+        // The directory wins over the language, and keeps the language's AST-node floor
+        assert_eq!(settings.threshold(&file(&generated)), 20);
+        assert_eq!(settings.min_ast_nodes(&file(&generated)), 15);
+        assert_eq!(settings.threshold(&file(&deeper)), 30);
+        assert_eq!(settings.threshold("not/selected.rs"), 5);
+    }
+}
@@ -1,61 +1,118 @@
+use crate::models::report::{SkipReason, SkippedFile};
 use crate::utils::language_mapping::get_language_mapping;
 use glob::Pattern;
+use ignore::WalkBuilder;
 use std::fs;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+/// Per-directory ignore file read alongside `.gitignore`, with the same syntax
+pub const IGNORE_FILE_NAME: &str = ".resonignore";
+
+/// Files selected for analysis, and the files passed over with the reason why
+///
+/// Excluded files are left out of both lists, since the user asked not to see them.
+#[derive(Debug, Default)]
+pub struct FileSelection {
+    pub files: Vec<PathBuf>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Whether a file matches one of the exclude glob patterns
+pub fn is_excluded(file: &Path, excludes: &[String]) -> bool {
+    excludes
+        .iter()
+        .any(|pattern| Pattern::new(pattern).unwrap().matches_path(file))
+}
+
+/// Every file under a directory, leaving out what ignore files exclude when `respect_ignore`
+///
+/// The ignore files are `.gitignore`, `.ignore`, `.git/info/exclude`, git's global
+/// excludes file and `.resonignore`, in the directory and its parents. `.gitignore`
+/// applies outside git repositories too. Hidden files are walked as before, except git's own `.git`
+/// directory.
+fn walk_files(dir: &Path, respect_ignore: bool) -> Vec<PathBuf> {
+    if !respect_ignore {
+        return WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.path().to_path_buf())
+            .collect();
+    }
+
+    WalkBuilder::new(dir)
+        .hidden(false)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
 /// Filters files based on glob patterns and returns matched file paths
 pub fn filter_files(
     source_path: &Path,
     languages: &[String],
     excludes: &[String],
     max_file_size: u64,
-) -> Vec<PathBuf> {
+    respect_ignore: bool,
+) -> FileSelection {
     let language_mapping = get_language_mapping();
     let valid_extensions: Vec<&str> = if languages.is_empty() {
-        language_mapping.values().flatten().map(|s| *s).collect()
+        language_mapping.values().flatten().copied().collect()
     } else {
         languages
             .iter()
             .filter_map(|lang| language_mapping.get(lang.as_str()))
             .flatten()
-            .map(|s| *s)
+            .copied()
             .collect()
     };
 
-    if source_path.is_file() {
-        let extension = source_path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("");
-        let metadata = fs::metadata(source_path).unwrap();
-        return if excludes
-            .iter()
-            .any(|pattern| Pattern::new(pattern).unwrap().matches_path(source_path))
-            || !valid_extensions.contains(&extension)
-            || metadata.len() > max_file_size
-        {
-            vec![]
+    let candidates: Vec<PathBuf> = if source_path.is_file() {
+        vec![source_path.to_path_buf()]
+    } else {
+        walk_files(source_path, respect_ignore)
+    };
+
+    let mut selection = FileSelection::default();
+    for file in candidates {
+        if is_excluded(&file, excludes) {
+            continue;
+        }
+
+        let extension = file.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let (reason, detail) = if !valid_extensions.contains(&extension) {
+            (SkipReason::UnsupportedExtension, None)
         } else {
-            vec![source_path.to_path_buf()]
+            match fs::metadata(&file) {
+                Ok(metadata) if metadata.len() > max_file_size => (
+                    SkipReason::TooLarge,
+                    Some(format!("{} bytes, limit {}", metadata.len(), max_file_size)),
+                ),
+                Ok(_) => {
+                    selection.files.push(file);
+                    continue;
+                }
+                Err(error) => (SkipReason::ParseFailure, Some(error.to_string())),
+            }
         };
+        selection.skipped.push(SkippedFile {
+            path: file.to_string_lossy().to_string(),
+            reason,
+            detail,
+        });
     }
 
-    WalkDir::new(source_path)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().is_file())
-        .map(|entry| entry.path().to_path_buf())
-        .filter(|file| {
-            let extension = file.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-            let metadata = fs::metadata(file).unwrap();
-            !excludes
-                .iter()
-                .any(|pattern| Pattern::new(pattern).unwrap().matches_path(file))
-                && valid_extensions.contains(&extension)
-                && metadata.len() <= max_file_size
-        })
-        .collect()
+    selection
 }
 
 #[cfg(test)]
@@ -70,7 +127,8 @@ mod tests {
         let excludes = vec!["*.rs".to_string()];
         let languages = vec!["rust".to_string()];
         let max_file_size = 1048576;
-        let filtered_files = filter_files(&file_path, &languages, &excludes, max_file_size);
+        let filtered_files =
+            filter_files(file_path, &languages, &excludes, max_file_size, true).files;
 
         assert!(filtered_files.is_empty());
     }
@@ -82,7 +140,8 @@ mod tests {
         let excludes = vec!["*.txt".to_string()];
         let languages = vec!["rust".to_string()];
         let max_file_size = 1048576;
-        let filtered_files = filter_files(&file_path, &languages, &excludes, max_file_size);
+        let filtered_files =
+            filter_files(file_path, &languages, &excludes, max_file_size, true).files;
 
         assert_eq!(filtered_files.len(), 1);
         assert_eq!(filtered_files[0], file_path);
@@ -99,14 +158,21 @@ mod tests {
         let excludes = vec![];
         let languages = vec!["rust".to_string()];
         let max_file_size = 1048576; // 1 MB
-        let filtered_files = filter_files(
-            &large_file_path.parent().unwrap(),
+        let selection = filter_files(
+            large_file_path.parent().unwrap(),
             &languages,
             &excludes,
             max_file_size,
+            true,
         );
 
-        assert!(!filtered_files.contains(&large_file_path));
+        assert!(!selection.files.contains(&large_file_path));
+        let skipped = selection
+            .skipped
+            .iter()
+            .find(|skipped| Path::new(&skipped.path) == large_file_path)
+            .expect("the large file should be listed as skipped");
+        assert_eq!(skipped.reason, SkipReason::TooLarge);
 
         // Clean up
         delete_temp_file(&large_file_path);
@@ -120,7 +186,8 @@ mod tests {
         let excludes = vec![];
         let languages = vec!["rust".to_string()];
         let max_file_size = 1048576; // 1 MB
-        let filtered_files = filter_files(test_dir, &languages, &excludes, max_file_size);
+        let filtered_files =
+            filter_files(test_dir, &languages, &excludes, max_file_size, true).files;
 
         assert!(filtered_files.contains(&small_file_path));
     }
@@ -132,11 +199,91 @@ mod tests {
         let excludes = vec!["*.txt".to_string()];
         let languages = vec!["rust".to_string()];
         let max_file_size = 1048576;
-        let filtered_files = filter_files(test_dir, &languages, &excludes, max_file_size);
+        let filtered_files =
+            filter_files(test_dir, &languages, &excludes, max_file_size, true).files;
 
         assert_eq!(filtered_files.len(), 3);
         assert!(filtered_files.contains(&test_dir.join("testA.rs")));
         assert!(filtered_files.contains(&test_dir.join("testB.rs")));
         assert!(filtered_files.contains(&test_dir.join("testC.rs")));
     }
+
+    #[test]
+    fn test_filter_files_lists_unsupported_extensions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "notes").unwrap();
+        fs::write(temp_dir.path().join("script.py"), "pass").unwrap();
+        fs::write(temp_dir.path().join("ignored.log"), "log").unwrap();
+
+        let languages = vec!["rust".to_string()];
+        let excludes = vec!["*.log".to_string()];
+        let selection = filter_files(temp_dir.path(), &languages, &excludes, 1048576, true);
+
+        assert_eq!(selection.files, vec![temp_dir.path().join("main.rs")]);
+        let mut skipped: Vec<(String, SkipReason)> = selection
+            .skipped
+            .into_iter()
+            .map(|skipped| (skipped.path, skipped.reason))
+            .collect();
+        skipped.sort_by(|left, right| left.0.cmp(&right.0));
+        assert_eq!(
+            skipped,
+            vec![
+                (
+                    temp_dir
+                        .path()
+                        .join("notes.txt")
+                        .to_string_lossy()
+                        .to_string(),
+                    SkipReason::UnsupportedExtension
+                ),
+                (
+                    temp_dir
+                        .path()
+                        .join("script.py")
+                        .to_string_lossy()
+                        .to_string(),
+                    SkipReason::UnsupportedExtension
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_files_honors_ignore_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::create_dir_all(root.join("vendor")).unwrap();
+        fs::create_dir_all(root.join("src/generated")).unwrap();
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        fs::write(root.join(".git/info/exclude"), "scratch.rs\n").unwrap();
+        fs::write(root.join(".ignore"), "vendor/\n").unwrap();
+        fs::write(root.join("src/.resonignore"), "generated/\n").unwrap();
+        for file in [
+            "main.rs",
+            "scratch.rs",
+            "build/out.rs",
+            "vendor/dep.rs",
+            "src/lib.rs",
+            "src/generated/api.rs",
+        ] {
+            fs::write(root.join(file), "fn main() {}").unwrap();
+        }
+
+        let languages = vec!["rust".to_string()];
+        let selection = filter_files(root, &languages, &[], 1048576, true);
+        assert!(selection
+            .skipped
+            .iter()
+            .all(|skipped| !Path::new(&skipped.path).starts_with(root.join(".git"))));
+        let mut files = selection.files;
+        files.sort();
+        assert_eq!(files, vec![root.join("main.rs"), root.join("src/lib.rs")]);
+
+        let files = filter_files(root, &languages, &[], 1048576, false).files;
+        assert_eq!(files.len(), 6);
+    }
 }
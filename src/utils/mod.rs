@@ -1,4 +1,8 @@
+pub mod baseline;
+pub mod config;
 pub mod files;
 pub mod filters;
 pub mod language_mapping;
 pub mod output;
+pub mod quality_gate;
+pub mod snippets;
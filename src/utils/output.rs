@@ -1,24 +1,73 @@
-use crate::models::detection_types::DuplicateReportXML;
-use quick_xml::se::to_string;
-use serde::Serialize;
+mod checkstyle;
+mod codeclimate;
+mod csv;
+mod html;
+mod junit;
+mod markdown;
+mod sarif;
+mod text;
+mod xml;
+
+use crate::models::detection_types::DuplicateBlock;
+use crate::models::report::Report;
 use std::fs::File;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::Path;
 
-/// Write output in JSON or other formats
-pub fn write_output<T: Serialize>(
-    results: &T,
+/// `file:start-end` of a block, followed by its context when it has one
+fn block_label(block: &DuplicateBlock) -> String {
+    let location = format!(
+        "{}:{}-{}",
+        block.source_file, block.start_line_number, block.end_line_number
+    );
+    match &block.context {
+        Some(context) => format!("{} ({})", location, context),
+        None => location,
+    }
+}
+
+/// Output goes to stdout when no file is given or the file is `-`
+fn writes_to_stdout(output_file: Option<&Path>) -> bool {
+    output_file.is_none_or(|file_path| file_path == Path::new("-"))
+}
+
+/// Color is used only on an interactive stdout, and never when `NO_COLOR` is set
+fn use_color(output_file: Option<&Path>) -> bool {
+    writes_to_stdout(output_file)
+        && std::io::stdout().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Write a report in JSON or other formats
+pub fn write_output(
+    report: &Report,
     output_format: &str,
     output_file: Option<&Path>,
 ) -> Result<(), std::io::Error> {
+    let records = &report.records;
     let output = match output_format {
-        "json" => serde_json::to_string_pretty(results)?,
-        "xml" => {
-            // Wrap results in a root element with a name
-            let wrapped = DuplicateReportXML { items: results };
-            to_string(&wrapped)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?
+        "json" => serde_json::to_string_pretty(report)?,
+        "xml" => xml::to_xml(
+            records,
+            &report.resolved,
+            &report.suppressed,
+            &report.summary,
+        )?,
+        "csv" => csv::to_csv(records, &report.summary),
+        "checkstyle" => checkstyle::to_checkstyle(records)?,
+        "junit" => junit::to_junit(records, &report.summary)?,
+        "codeclimate" | "gitlab" => {
+            serde_json::to_string_pretty(&codeclimate::to_codeclimate(records))?
         }
+        "markdown" => markdown::to_markdown(records, &report.summary),
+        "html" => html::to_html(records, &report.summary),
+        "sarif" => serde_json::to_string_pretty(&sarif::to_sarif(
+            records,
+            &report.resolved,
+            &report.suppressed,
+            &report.summary,
+        ))?,
+        "text" => text::to_text(records, &report.summary, use_color(output_file)),
         _ => {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -27,11 +76,12 @@ pub fn write_output<T: Serialize>(
         }
     };
 
-    if let Some(file_path) = output_file {
-        let mut file = File::create(file_path)?;
-        file.write_all(output.as_bytes())?;
-    } else {
-        println!("{}", output);
+    match output_file {
+        Some(file_path) if !writes_to_stdout(output_file) => {
+            let mut file = File::create(file_path)?;
+            file.write_all(output.as_bytes())?;
+        }
+        _ => println!("{}", output),
     }
 
     Ok(())
@@ -40,74 +90,177 @@ pub fn write_output<T: Serialize>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::detection_types::{DuplicateBlock, DuplicateReport};
-    use serde_json::json;
+    use crate::models::code_types::CloneType;
+    use crate::models::detection_types::{DuplicateBlock, DuplicateReport, Snippet};
+    use crate::models::report::{AnalyzedFile, Metadata, ReportOptions};
     use std::fs;
     use tempfile;
 
+    fn sample_report() -> Report {
+        let block = |source_file: &str| DuplicateBlock {
+            start_line_number: 121,
+            end_line_number: 139,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        };
+        let records = vec![DuplicateReport {
+            fingerprint: "f40bd2979a68336ba4862f08d3372ef5f8b369172b4c38bd9039031dce0a084b"
+                .to_string(),
+            clone_type: CloneType::Parameterized,
+            line_count: 19,
+            similarity: None,
+            baseline_status: None,
+            blocks: vec![block("./rtos/file1.c"), block("./rtos/file2.c")],
+        }];
+        let analyzed: Vec<AnalyzedFile> = ["./rtos/file1.c", "./rtos/file2.c", "./rtos/queue.h"]
+            .iter()
+            .map(|path| AnalyzedFile {
+                path: path.to_string(),
+                language: "c".to_string(),
+                lines: 200,
+            })
+            .collect();
+        Report::new(records, &analyzed, Metadata::new(ReportOptions::default()))
+    }
+
     #[test]
     fn test_write_output_json() {
-        let results = json!([{
-            "fingerprint": "f40bd2979a68336ba4862f08d3372ef5f8b369172b4c38bd9039031dce0a084b",
-            "line_count": 19,
-            "blocks": [
-                {"start_line_number": 121, "end_line_number": 139, "source_file": "./file1.c"},
-                {"start_line_number": 121, "end_line_number": 139, "source_file": "./file2.c"}
-            ]
-        }]);
+        let report = sample_report();
         let output_format = "json";
         let temp_file = tempfile::NamedTempFile::new().unwrap();
         let output_file = Some(temp_file.path());
 
-        write_output(&results, output_format, output_file).unwrap();
+        write_output(&report, output_format, output_file).unwrap();
 
         let written_content = fs::read_to_string(temp_file.path()).unwrap();
         assert!(written_content
             .contains("f40bd2979a68336ba4862f08d3372ef5f8b369172b4c38bd9039031dce0a084b"));
+        let parsed: Report = serde_json::from_str(&written_content).unwrap();
+        assert_eq!(parsed, report);
     }
 
     #[test]
     fn test_write_output_xml() {
-        let results = vec![DuplicateReport {
-            fingerprint: "f40bd2979a68336ba4862f08d3372ef5f8b369172b4c38bd9039031dce0a084b"
-                .to_string(),
-            line_count: 19,
-            blocks: vec![
-                DuplicateBlock {
-                    start_line_number: 121,
-                    end_line_number: 139,
-                    source_file: "./rtos/file1.c".to_string(),
-                },
-                DuplicateBlock {
-                    start_line_number: 121,
-                    end_line_number: 139,
-                    source_file: "./rtos/file2.c".to_string(),
-                },
-            ],
-        }];
+        let mut report = sample_report();
+        let first = &mut report.records[0].blocks[0];
+        first.symbol_name = Some("init_queue".to_string());
+        first.symbol_kind = Some("function".to_string());
+        first.context = Some("init_queue".to_string());
+        first.snippet = Some(Snippet {
+            start_byte: 2048,
+            end_byte: 2098,
+            start_column: 1,
+            end_column: 2,
+            text: "void init_queue(queue_t *q) {\n    q->head = 0;\n}".to_string(),
+            truncated: false,
+        });
+        first.byte_range = Some((2048, 2098));
 
         let temp_file = tempfile::NamedTempFile::new().unwrap();
-        write_output(&results, "xml", Some(temp_file.path())).unwrap();
+        write_output(&report, "xml", Some(temp_file.path())).unwrap();
 
         let written_content = fs::read_to_string(temp_file.path()).unwrap();
         println!("{}", written_content);
-        assert!(written_content.contains("<fingerprint>"));
+        assert!(written_content
+            .contains("<summary clones=\"1\" blocks=\"2\" lines=\"38\" files=\"2\" total-lines=\"600\" unique-lines=\"38\""));
+        assert!(written_content.contains("<clone fingerprint=\"f40bd2979a68336ba4862f08d3372ef5f8b369172b4c38bd9039031dce0a084b\" type=\"parameterized\" lines=\"19\">"));
+        assert!(written_content.contains("<snippet start-byte=\"2048\" end-byte=\"2098\""));
+        assert!(written_content.contains("q-&gt;head = 0;"));
+        assert!(
+            written_content.contains("<block file=\"./rtos/file2.c\" start=\"121\" end=\"139\"/>")
+        );
+        assert!(!written_content.contains("byte_range"));
+    }
+
+    #[test]
+    fn test_write_output_sarif() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        write_output(&sample_report(), "sarif", Some(temp_file.path())).unwrap();
+
+        let written_content = fs::read_to_string(temp_file.path()).unwrap();
+        let log: serde_json::Value = serde_json::from_str(&written_content).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0]["relatedLocations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "rtos/file2.c"
+        );
+        let summary = &log["runs"][0]["properties"]["summary"];
+        assert_eq!(summary["totalLines"], 600);
+        assert_eq!(summary["languages"][0]["name"], "c");
+    }
+
+    #[test]
+    fn test_write_output_text() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        write_output(&sample_report(), "text", Some(temp_file.path())).unwrap();
+
+        let written_content = fs::read_to_string(temp_file.path()).unwrap();
+        assert!(!written_content.contains('\x1b'));
+        assert!(written_content.contains("./rtos/file2.c:121-139"));
+        assert!(written_content.contains("2 duplicate blocks, 38 duplicate lines in 2 files"));
+    }
+
+    #[test]
+    fn test_write_output_csv_and_markdown() {
+        let report = sample_report();
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+
+        write_output(&report, "csv", Some(temp_file.path())).unwrap();
+        let written_content = fs::read_to_string(temp_file.path()).unwrap();
+        assert_eq!(written_content.lines().count(), 3);
+        assert!(written_content.contains(
+            "1,f40bd2979a68336ba4862f08d3372ef5f8b369172b4c38bd9039031dce0a084b,./rtos/file2.c,121,139,19,200,19,0.0950"
+        ));
+
+        write_output(&report, "markdown", Some(temp_file.path())).unwrap();
+        let written_content = fs::read_to_string(temp_file.path()).unwrap();
+        assert!(written_content.contains("| 1 | 2 | 38 | 2 |"));
+        assert!(written_content.contains("./rtos/file1.c:121-139<br>./rtos/file2.c:121-139"));
+    }
+
+    #[test]
+    fn test_write_output_checkstyle_and_codeclimate() {
+        let report = sample_report();
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+
+        write_output(&report, "checkstyle", Some(temp_file.path())).unwrap();
+        let written_content = fs::read_to_string(temp_file.path()).unwrap();
+        assert!(written_content.contains("<file name=\"./rtos/file1.c\"><error line=\"121\""));
+
+        for format in ["codeclimate", "gitlab"] {
+            write_output(&report, format, Some(temp_file.path())).unwrap();
+            let written_content = fs::read_to_string(temp_file.path()).unwrap();
+            let issues: serde_json::Value = serde_json::from_str(&written_content).unwrap();
+            assert_eq!(issues.as_array().unwrap().len(), 2);
+            assert_eq!(issues[1]["location"]["path"], "rtos/file2.c");
+        }
+    }
+
+    #[test]
+    fn test_write_output_junit() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        write_output(&sample_report(), "junit", Some(temp_file.path())).unwrap();
+
+        let written_content = fs::read_to_string(temp_file.path()).unwrap();
+        assert!(written_content.contains("tests=\"3\" failures=\"2\""));
+        assert!(written_content
+            .contains("<testcase classname=\"duplicate-code\" name=\"./rtos/queue.h\"/>"));
+        assert!(written_content.contains("name=\"./rtos/file2.c\"><failure"));
     }
 
     #[test]
     fn test_write_output_unsupported_format() {
-        let results = json!([{
-            "fingerprint": "f40bd2979a68336ba4862f08d3372ef5f8b369172b4c38bd9039031dce0a084b",
-            "line_count": 19,
-            "blocks": [
-                {"start_line_number": 121, "end_line_number": 139, "source_file": "./file1.c"}
-            ]
-        }]);
         let output_format = "yaml";
         let temp_file = tempfile::NamedTempFile::new().unwrap();
         let output_file = Some(temp_file.path());
 
-        let result = write_output(&results, output_format, output_file);
+        let result = write_output(&sample_report(), output_format, output_file);
         assert!(result.is_err());
     }
 }
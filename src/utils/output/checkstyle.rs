@@ -0,0 +1,151 @@
+use crate::models::detection_types::{DuplicateBlock, DuplicateReport};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const CHECKSTYLE_VERSION: &str = "4.3";
+const SOURCE: &str = "reson.duplicate-code";
+
+#[derive(Serialize)]
+#[serde(rename = "checkstyle")]
+struct Checkstyle {
+    #[serde(rename = "@version")]
+    version: &'static str,
+    #[serde(rename = "file")]
+    files: Vec<CheckstyleFile>,
+}
+
+#[derive(Serialize)]
+struct CheckstyleFile {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "error")]
+    errors: Vec<CheckstyleError>,
+}
+
+#[derive(Serialize)]
+struct CheckstyleError {
+    #[serde(rename = "@line")]
+    line: usize,
+    #[serde(rename = "@column", skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    #[serde(rename = "@severity")]
+    severity: &'static str,
+    #[serde(rename = "@message")]
+    message: String,
+    #[serde(rename = "@source")]
+    source: &'static str,
+}
+
+fn block_location(block: &DuplicateBlock) -> String {
+    format!(
+        "{}:{}-{}",
+        block.source_file, block.start_line_number, block.end_line_number
+    )
+}
+
+/// Checkstyle XML with one warning per duplicate block, grouped by file
+pub fn to_checkstyle(reports: &[DuplicateReport]) -> Result<String, std::io::Error> {
+    let mut files: BTreeMap<&str, Vec<CheckstyleError>> = BTreeMap::new();
+    for report in reports {
+        for (index, block) in report.blocks.iter().enumerate() {
+            let others: Vec<String> = report
+                .blocks
+                .iter()
+                .enumerate()
+                .filter(|(other_index, _)| *other_index != index)
+                .map(|(_, other)| block_location(other))
+                .collect();
+            files
+                .entry(block.source_file.as_str())
+                .or_default()
+                .push(CheckstyleError {
+                    line: block.start_line_number,
+                    column: block.snippet.as_ref().map(|snippet| snippet.start_column),
+                    severity: "warning",
+                    message: format!(
+                        "Duplicated code: {} lines (lines {}-{}) also found in {} [{}]",
+                        report.line_count,
+                        block.start_line_number,
+                        block.end_line_number,
+                        others.join(", "),
+                        report.fingerprint
+                    ),
+                    source: SOURCE,
+                });
+        }
+    }
+
+    let checkstyle = Checkstyle {
+        version: CHECKSTYLE_VERSION,
+        files: files
+            .into_iter()
+            .map(|(name, mut errors)| {
+                errors.sort_by_key(|error| error.line);
+                CheckstyleFile {
+                    name: name.to_string(),
+                    errors,
+                }
+            })
+            .collect(),
+    };
+    let xml =
+        quick_xml::se::to_string(&checkstyle).map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+        xml
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+
+    fn block(source_file: &str, start: usize) -> DuplicateBlock {
+        DuplicateBlock {
+            start_line_number: start,
+            end_line_number: start + 4,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        }
+    }
+
+    #[test]
+    fn test_to_checkstyle() {
+        let reports = vec![
+            DuplicateReport {
+                fingerprint: "aa".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 5,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/b.c", 30), block("src/a&b.c", 1)],
+            },
+            DuplicateReport {
+                fingerprint: "bb".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 5,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/b.c", 10), block("src/c.c", 10)],
+            },
+        ];
+
+        let xml = to_checkstyle(&reports).unwrap();
+        assert!(xml.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">"
+        ));
+        assert!(xml.contains("<file name=\"src/a&amp;b.c\"><error line=\"1\" severity=\"warning\" message=\"Duplicated code: 5 lines (lines 1-5) also found in src/b.c:30-34 [aa]\" source=\"reson.duplicate-code\"/></file>"));
+
+        // Files are sorted and errors within a file are ordered by line
+        let a = xml.find("name=\"src/a&amp;b.c\"").unwrap();
+        let b = xml.find("name=\"src/b.c\"").unwrap();
+        let c = xml.find("name=\"src/c.c\"").unwrap();
+        assert!(a < b && b < c);
+        assert!(xml.find("line=\"10\"").unwrap() < xml.find("line=\"30\"").unwrap());
+    }
+}
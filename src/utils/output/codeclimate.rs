@@ -0,0 +1,159 @@
+use crate::models::detection_types::{DuplicateBlock, DuplicateReport};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const CHECK_NAME: &str = "duplicate-code";
+
+/// Paths as GitLab expects them: relative to the project root with forward slashes
+fn issue_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_string()
+}
+
+/// A fingerprint that stays the same while the clone class and file are unchanged
+///
+/// Line numbers are left out so that moving a clone within its file keeps the issue.
+/// Blocks of one class in the same file are told apart by their order in that file.
+fn issue_fingerprint(clone_fingerprint: &str, path: &str, occurrence: usize) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(clone_fingerprint.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(occurrence.to_string().as_bytes());
+    hasher.finalize().to_hex()[..32].to_string()
+}
+
+fn location(block: &DuplicateBlock) -> Value {
+    json!({
+        "path": issue_path(&block.source_file),
+        "lines": {
+            "begin": block.start_line_number,
+            "end": block.end_line_number,
+        },
+    })
+}
+
+/// One Code Climate issue per duplicate block, which is the format GitLab Code Quality reads
+pub fn to_codeclimate(reports: &[DuplicateReport]) -> Value {
+    let mut issues = Vec::new();
+    for report in reports {
+        let mut order: Vec<usize> = (0..report.blocks.len()).collect();
+        order.sort_by_key(|&index| {
+            let block = &report.blocks[index];
+            (issue_path(&block.source_file), block.start_line_number)
+        });
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        let mut fingerprints = vec![String::new(); report.blocks.len()];
+        for index in order {
+            let path = issue_path(&report.blocks[index].source_file);
+            let occurrence = occurrences.entry(path.clone()).or_insert(0);
+            fingerprints[index] = issue_fingerprint(&report.fingerprint, &path, *occurrence);
+            *occurrence += 1;
+        }
+
+        for (index, block) in report.blocks.iter().enumerate() {
+            let others: Vec<&DuplicateBlock> = report
+                .blocks
+                .iter()
+                .enumerate()
+                .filter(|(other_index, _)| *other_index != index)
+                .map(|(_, other)| other)
+                .collect();
+            let description = format!(
+                "Duplicated code: {} lines also found in {}",
+                report.line_count,
+                others
+                    .iter()
+                    .map(|other| format!(
+                        "{}:{}-{}",
+                        issue_path(&other.source_file),
+                        other.start_line_number,
+                        other.end_line_number
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+            issues.push(json!({
+                "type": "issue",
+                "check_name": CHECK_NAME,
+                "description": description,
+                "categories": ["Duplication"],
+                "severity": "minor",
+                "fingerprint": fingerprints[index],
+                "location": location(block),
+                "other_locations": others.iter().map(|other| location(other)).collect::<Vec<Value>>(),
+            }));
+        }
+    }
+    Value::Array(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+
+    fn block(source_file: &str, start: usize) -> DuplicateBlock {
+        DuplicateBlock {
+            start_line_number: start,
+            end_line_number: start + 9,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        }
+    }
+
+    fn report(blocks: Vec<DuplicateBlock>) -> DuplicateReport {
+        DuplicateReport {
+            fingerprint: "f40bd297".to_string(),
+            clone_type: CloneType::Parameterized,
+            line_count: 10,
+            similarity: None,
+            baseline_status: None,
+            blocks,
+        }
+    }
+
+    #[test]
+    fn test_to_codeclimate() {
+        let issues = to_codeclimate(&[report(vec![
+            block("./src/a.c", 5),
+            block("./src/b.c", 40),
+            block("./src/b.c", 10),
+        ])]);
+        let issues = issues.as_array().unwrap();
+        assert_eq!(issues.len(), 3);
+
+        assert_eq!(issues[0]["check_name"], "duplicate-code");
+        assert_eq!(issues[0]["location"]["path"], "src/a.c");
+        assert_eq!(issues[0]["location"]["lines"]["begin"], 5);
+        assert_eq!(issues[0]["other_locations"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            issues[0]["description"],
+            "Duplicated code: 10 lines also found in src/b.c:40-49, src/b.c:10-19"
+        );
+
+        // Fingerprints are unique, and blocks in the same file are numbered by position
+        let fingerprints: std::collections::HashSet<&str> = issues
+            .iter()
+            .map(|issue| issue["fingerprint"].as_str().unwrap())
+            .collect();
+        assert_eq!(fingerprints.len(), 3);
+        assert_eq!(
+            issues[2]["fingerprint"],
+            issue_fingerprint("f40bd297", "src/b.c", 0)
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_survives_line_shifts() {
+        let before = to_codeclimate(&[report(vec![block("src/a.c", 5), block("src/b.c", 10)])]);
+        let after = to_codeclimate(&[report(vec![block("src/a.c", 25), block("src/b.c", 10)])]);
+        assert_eq!(before[0]["fingerprint"], after[0]["fingerprint"]);
+        assert_ne!(before[0]["fingerprint"], before[1]["fingerprint"]);
+    }
+}
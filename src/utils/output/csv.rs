@@ -0,0 +1,123 @@
+use crate::models::detection_types::DuplicateReport;
+use crate::models::report::{Breakdown, Summary};
+use std::collections::HashMap;
+
+const HEADER: &str = "class_id,fingerprint,file,start_line,end_line,line_count,\
+                      file_lines,file_duplicated_lines,file_duplication_ratio";
+
+/// Quote a field when it contains a delimiter, quote or line break, as in RFC 4180
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One row per duplicate block, with clone classes numbered from 1 in report order
+///
+/// `line_count` is the length of the block itself, since the blocks of a near-miss or
+/// statement-sequence class can differ in length. Each row repeats the duplication of
+/// its file; the file columns are empty when the summary does not list the file.
+pub fn to_csv(reports: &[DuplicateReport], summary: &Summary) -> String {
+    let files: HashMap<&str, &Breakdown> = summary
+        .files
+        .iter()
+        .map(|file| (file.name.as_str(), file))
+        .collect();
+    let mut csv = String::from(HEADER);
+    csv.push('\n');
+    for (index, report) in reports.iter().enumerate() {
+        for block in &report.blocks {
+            let file = files
+                .get(block.source_file.as_str())
+                .map(|file| {
+                    format!(
+                        "{},{},{:.4}",
+                        file.total_lines, file.duplicated_lines, file.duplication_ratio
+                    )
+                })
+                .unwrap_or_else(|| ",,".to_string());
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                index + 1,
+                field(&report.fingerprint),
+                field(&block.source_file),
+                block.start_line_number,
+                block.end_line_number,
+                block.end_line_number - block.start_line_number + 1,
+                file
+            ));
+        }
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+    use crate::models::detection_types::DuplicateBlock;
+    use crate::models::report::AnalyzedFile;
+
+    fn block(source_file: &str, start: usize, end: usize) -> DuplicateBlock {
+        DuplicateBlock {
+            start_line_number: start,
+            end_line_number: end,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        }
+    }
+
+    #[test]
+    fn test_field() {
+        assert_eq!(field("src/a.c"), "src/a.c");
+        assert_eq!(field("src/a,b.c"), "\"src/a,b.c\"");
+        assert_eq!(field("say \"hi\".c"), "\"say \"\"hi\"\".c\"");
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let reports = vec![
+            DuplicateReport {
+                fingerprint: "aa".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 7,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/a.c", 1, 7), block("src/b,c.c", 10, 16)],
+            },
+            DuplicateReport {
+                fingerprint: "bb".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 6,
+                similarity: Some(0.9),
+                baseline_status: None,
+                blocks: vec![block("src/a.c", 20, 24), block("src/a.c", 30, 35)],
+            },
+        ];
+
+        let analyzed = [AnalyzedFile {
+            path: "src/a.c".to_string(),
+            language: "c".to_string(),
+            lines: 40,
+        }];
+        let summary = Summary::new(&reports, &analyzed);
+
+        assert_eq!(
+            to_csv(&reports, &summary),
+            "class_id,fingerprint,file,start_line,end_line,line_count,\
+             file_lines,file_duplicated_lines,file_duplication_ratio\n\
+             1,aa,src/a.c,1,7,7,40,18,0.4500\n\
+             1,aa,\"src/b,c.c\",10,16,7,0,7,0.0000\n\
+             2,bb,src/a.c,20,24,5,40,18,0.4500\n\
+             2,bb,src/a.c,30,35,6,40,18,0.4500\n"
+        );
+        assert!(to_csv(&reports, &Summary::default()).contains("\n1,aa,src/a.c,1,7,7,,,\n"));
+        assert_eq!(to_csv(&[], &Summary::default()), format!("{}\n", HEADER));
+    }
+}
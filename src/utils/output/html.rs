@@ -0,0 +1,475 @@
+use super::block_label;
+use crate::models::detection_types::{DuplicateBlock, DuplicateReport};
+use crate::models::report::{Breakdown, Summary};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 0; color: #1f2328; background: #f6f8fa; }
+header { background: #24292f; color: #fff; padding: 16px 32px; }
+header h1 { margin: 0; font-size: 22px; }
+header p { margin: 4px 0 0; color: #c9d1d9; font-size: 13px; }
+main { padding: 16px 32px; }
+section { margin-bottom: 32px; }
+.dashboard { display: flex; gap: 16px; flex-wrap: wrap; }
+.card { background: #fff; border: 1px solid #d0d7de; border-radius: 6px; padding: 16px 24px; min-width: 160px; }
+.card .value { font-size: 28px; font-weight: 600; }
+.card .label { color: #57606a; font-size: 13px; }
+table { border-collapse: collapse; width: 100%; background: #fff; font-size: 13px; }
+th, td { border: 1px solid #d0d7de; padding: 6px 10px; text-align: left; vertical-align: top; }
+th { background: #eaeef2; cursor: pointer; user-select: none; white-space: nowrap; }
+th.asc::after { content: " \25B2"; }
+th.desc::after { content: " \25BC"; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+.bar { background: #eaeef2; height: 8px; border-radius: 4px; min-width: 80px; }
+.bar span { display: block; height: 8px; border-radius: 4px; background: #cf222e; }
+details { background: #fff; border: 1px solid #d0d7de; border-radius: 6px; margin-bottom: 12px; }
+summary { padding: 8px 12px; cursor: pointer; font-weight: 600; }
+.pair { display: grid; grid-template-columns: 1fr 1fr; gap: 8px; padding: 0 12px 12px; }
+.side { min-width: 0; }
+.side .title { font-size: 12px; color: #57606a; padding: 4px 0; word-break: break-all; }
+pre { margin: 0; background: #f6f8fa; border: 1px solid #d0d7de; border-radius: 6px; overflow-x: auto; font-size: 12px; line-height: 1.5; }
+.line { display: block; padding-right: 8px; white-space: pre; }
+.line.changed { background: #fff8c5; }
+.ln { display: inline-block; width: 48px; padding-right: 8px; text-align: right; color: #8c959f; user-select: none; }
+.missing { padding: 8px; color: #57606a; font-style: italic; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable").forEach(function (table) {
+  table.querySelectorAll("th").forEach(function (header, column) {
+    header.addEventListener("click", function () {
+      var ascending = !header.classList.contains("asc");
+      table.querySelectorAll("th").forEach(function (th) { th.classList.remove("asc", "desc"); });
+      header.classList.add(ascending ? "asc" : "desc");
+      var body = table.tBodies[0];
+      var rows = Array.prototype.slice.call(body.rows);
+      rows.sort(function (a, b) {
+        var left = a.cells[column].getAttribute("data-value") || a.cells[column].textContent;
+        var right = b.cells[column].getAttribute("data-value") || b.cells[column].textContent;
+        var order = header.dataset.type === "number"
+          ? parseFloat(left) - parseFloat(right)
+          : left.localeCompare(right);
+        return ascending ? order : -order;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+"#;
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Mark the lines of each side that are not part of their longest common subsequence
+///
+/// Lines are compared without surrounding whitespace. The subsequence is found with
+/// Hirschberg's algorithm, which needs memory linear in the block lengths.
+fn changed_lines(left: &[&str], right: &[&str]) -> (Vec<bool>, Vec<bool>) {
+    let left: Vec<&str> = left.iter().map(|line| line.trim()).collect();
+    let right: Vec<&str> = right.iter().map(|line| line.trim()).collect();
+    let mut left_changed = vec![true; left.len()];
+    let mut right_changed = vec![true; right.len()];
+    mark_common_lines(&left, &right, &mut left_changed, &mut right_changed);
+    (left_changed, right_changed)
+}
+
+/// Lengths of the longest common subsequences of `left` and each prefix of `right`
+fn common_lengths<'a>(left: impl Iterator<Item = &'a str>, right: &[&'a str]) -> Vec<usize> {
+    let mut lengths = vec![0usize; right.len() + 1];
+    for line in left {
+        let mut diagonal = 0;
+        for (j, other) in right.iter().enumerate() {
+            let above = lengths[j + 1];
+            lengths[j + 1] = if line == *other {
+                diagonal + 1
+            } else {
+                above.max(lengths[j])
+            };
+            diagonal = above;
+        }
+    }
+    lengths
+}
+
+fn mark_common_lines(
+    left: &[&str],
+    right: &[&str],
+    left_changed: &mut [bool],
+    right_changed: &mut [bool],
+) {
+    if left.is_empty() || right.is_empty() {
+        return;
+    }
+    if left.len() == 1 {
+        if let Some(j) = right.iter().position(|line| *line == left[0]) {
+            left_changed[0] = false;
+            right_changed[j] = false;
+        }
+        return;
+    }
+
+    // Split the right side where the halves of the left side share the most lines
+    let middle = left.len() / 2;
+    let forward = common_lengths(left[..middle].iter().copied(), right);
+    let reversed_right: Vec<&str> = right.iter().rev().copied().collect();
+    let backward = common_lengths(left[middle..].iter().rev().copied(), &reversed_right);
+    let split = (0..=right.len())
+        .max_by_key(|&j| (forward[j] + backward[right.len() - j], std::cmp::Reverse(j)))
+        .unwrap_or(0);
+
+    let (left_head, left_tail) = left_changed.split_at_mut(middle);
+    let (right_head, right_tail) = right_changed.split_at_mut(split);
+    mark_common_lines(&left[..middle], &right[..split], left_head, right_head);
+    mark_common_lines(&left[middle..], &right[split..], left_tail, right_tail);
+}
+
+/// Source files read on demand, each at most once
+struct SourceCache {
+    sources: HashMap<String, Option<String>>,
+}
+
+impl SourceCache {
+    fn get(&mut self, path: &str) -> Option<&str> {
+        self.sources
+            .entry(path.to_string())
+            .or_insert_with(|| fs::read_to_string(path).ok())
+            .as_deref()
+    }
+
+    fn block_lines(&mut self, block: &DuplicateBlock) -> Option<Vec<String>> {
+        let source = self.get(&block.source_file)?;
+        let lines: Vec<String> = source
+            .lines()
+            .skip(block.start_line_number.saturating_sub(1))
+            .take(block.end_line_number + 1 - block.start_line_number)
+            .map(str::to_string)
+            .collect();
+        (!lines.is_empty()).then_some(lines)
+    }
+}
+
+fn write_side(
+    html: &mut String,
+    block: &DuplicateBlock,
+    lines: Option<&[String]>,
+    changed: &[bool],
+) {
+    let _ = write!(
+        html,
+        "<div class=\"side\"><div class=\"title\">{}</div>",
+        escape(&block_label(block))
+    );
+    match lines {
+        Some(lines) => {
+            html.push_str("<pre><code>");
+            for (offset, line) in lines.iter().enumerate() {
+                let class = if changed.get(offset).copied().unwrap_or(false) {
+                    "line changed"
+                } else {
+                    "line"
+                };
+                let _ = write!(
+                    html,
+                    "<span class=\"{}\"><span class=\"ln\">{}</span>{}</span>",
+                    class,
+                    block.start_line_number + offset,
+                    escape(line)
+                );
+            }
+            html.push_str("</code></pre>");
+        }
+        None => html.push_str("<div class=\"missing\">Source not available</div>"),
+    }
+    html.push_str("</div>");
+}
+
+fn write_dashboard(html: &mut String, reports: &[DuplicateReport], summary: &Summary) {
+    html.push_str("<section class=\"dashboard\">");
+    let baseline = summary.baseline.as_ref().map(|baseline| {
+        [
+            (baseline.new.to_string(), "New clone classes"),
+            (baseline.resolved.to_string(), "Resolved clone classes"),
+        ]
+    });
+    for (value, label) in [
+        (reports.len().to_string(), "Clone classes"),
+        (summary.duplicate_blocks.to_string(), "Duplicate blocks"),
+        (summary.duplicate_lines.to_string(), "Duplicate lines"),
+        (summary.duplicate_files.to_string(), "Files with duplicates"),
+        (summary.total_lines.to_string(), "Lines analyzed"),
+        (
+            format!("{:.1}%", summary.duplication_ratio * 100.0),
+            "Duplication",
+        ),
+    ]
+    .into_iter()
+    .chain(baseline.into_iter().flatten())
+    {
+        let _ = write!(
+            html,
+            "<div class=\"card\"><div class=\"value\">{}</div><div class=\"label\">{}</div></div>",
+            value, label
+        );
+    }
+    html.push_str("</section>");
+}
+
+/// Clone classes are numbered from 1 in report order, as in the text report
+fn write_clone_table(html: &mut String, reports: &[DuplicateReport]) {
+    html.push_str(
+        "<section><h2>Clone classes</h2><table class=\"sortable\"><thead><tr>\
+         <th data-type=\"number\">#</th><th data-type=\"number\">Lines</th>\
+         <th data-type=\"number\">Blocks</th><th>Type</th>\
+         <th data-type=\"number\">Similarity</th><th>Locations</th>\
+         </tr></thead><tbody>",
+    );
+    for (index, report) in reports.iter().enumerate() {
+        let id = index + 1;
+        let clone_type = report.clone_type.as_str();
+        let similarity = report
+            .similarity
+            .map(|similarity| format!("{:.0}%", similarity * 100.0))
+            .unwrap_or_else(|| "100%".to_string());
+        let locations: Vec<String> = report
+            .blocks
+            .iter()
+            .map(|block| escape(&block_label(block)))
+            .collect();
+        let _ = write!(
+            html,
+            "<tr><td class=\"number\"><a href=\"#clone-{id}\">{id}</a></td>\
+             <td class=\"number\">{}</td><td class=\"number\">{}</td><td>{}</td>\
+             <td class=\"number\" data-value=\"{}\">{}</td><td>{}</td></tr>",
+            report.line_count,
+            report.blocks.len(),
+            clone_type,
+            report.similarity.unwrap_or(1.0),
+            similarity,
+            locations.join("<br>"),
+        );
+    }
+    html.push_str("</tbody></table></section>");
+}
+
+/// A sortable table of files, directories or languages with a bar for their duplication
+fn write_breakdown_table(html: &mut String, title: &str, column: &str, rows: &[Breakdown]) {
+    let _ = write!(
+        html,
+        "<section><h2>{}</h2><table class=\"sortable\"><thead><tr>\
+         <th>{}</th><th data-type=\"number\">Lines</th>\
+         <th data-type=\"number\">Duplicated lines</th>\
+         <th data-type=\"number\">Duplicated</th>\
+         </tr></thead><tbody>",
+        title, column
+    );
+    for row in rows {
+        // Files with blocks but no line count have no total to compare against
+        let total = Some(row.total_lines).filter(|&total| total > 0);
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td>",
+            escape(&row.name),
+            total.map_or("-".to_string(), |total| total.to_string()),
+            row.duplicated_lines
+        );
+        match total {
+            Some(_) => {
+                let percentage = row.duplication_ratio * 100.0;
+                let _ = write!(
+                    html,
+                    "<td class=\"number\" data-value=\"{percentage:.2}\">{percentage:.1}%\
+                     <div class=\"bar\"><span style=\"width: {percentage:.1}%\"></span></div></td></tr>"
+                );
+            }
+            None => html.push_str("<td class=\"number\" data-value=\"0\">-</td></tr>"),
+        }
+    }
+    html.push_str("</tbody></table></section>");
+}
+
+fn write_clone_pairs(html: &mut String, reports: &[DuplicateReport], sources: &mut SourceCache) {
+    html.push_str("<section><h2>Clone pairs</h2>");
+    for (index, report) in reports.iter().enumerate() {
+        let Some((first, siblings)) = report.blocks.split_first() else {
+            continue;
+        };
+        let _ = write!(
+            html,
+            "<details id=\"clone-{}\"><summary>Clone class {} &middot; {} lines &middot; {} blocks &middot; {}</summary>",
+            index + 1,
+            index + 1,
+            report.line_count,
+            report.blocks.len(),
+            escape(&report.fingerprint[..report.fingerprint.len().min(12)])
+        );
+        let first_lines = sources.block_lines(first);
+        for sibling in siblings {
+            let sibling_lines = sources.block_lines(sibling);
+            let (left_changed, right_changed) = match (&first_lines, &sibling_lines) {
+                (Some(left), Some(right)) => {
+                    let left: Vec<&str> = left.iter().map(String::as_str).collect();
+                    let right: Vec<&str> = right.iter().map(String::as_str).collect();
+                    changed_lines(&left, &right)
+                }
+                _ => (Vec::new(), Vec::new()),
+            };
+            html.push_str("<div class=\"pair\">");
+            write_side(html, first, first_lines.as_deref(), &left_changed);
+            write_side(html, sibling, sibling_lines.as_deref(), &right_changed);
+            html.push_str("</div>");
+        }
+        html.push_str("</details>");
+    }
+    html.push_str("</section>");
+}
+
+/// Render a single self-contained HTML page: a dashboard, sortable tables of clone
+/// classes, languages, directories and files, and a side-by-side view of every clone pair
+///
+/// Source files are read again to show the duplicated code; files that can no longer
+/// be read are reported without it.
+pub fn to_html(reports: &[DuplicateReport], summary: &Summary) -> String {
+    let mut sources = SourceCache {
+        sources: HashMap::new(),
+    };
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>Reson duplication report</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <header><h1>Reson duplication report</h1><p>Generated by reson {}</p></header>\n<main>\n",
+        STYLE,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    write_dashboard(&mut html, reports, summary);
+    write_clone_table(&mut html, reports);
+    write_breakdown_table(&mut html, "Languages", "Language", &summary.languages);
+    write_breakdown_table(&mut html, "Directories", "Directory", &summary.directories);
+    write_breakdown_table(&mut html, "Files", "File", &summary.files);
+    write_clone_pairs(&mut html, reports, &mut sources);
+
+    let _ = write!(
+        html,
+        "\n</main>\n<script>{}</script>\n</body>\n</html>\n",
+        SCRIPT
+    );
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+    use crate::models::report::AnalyzedFile;
+    use crate::utils::files::{create_temp_file, delete_temp_file};
+
+    fn block(source_file: &str, start: usize, end: usize) -> DuplicateBlock {
+        DuplicateBlock {
+            start_line_number: start,
+            end_line_number: end,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        }
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let left = ["a", "b", "c", "d"];
+        let right = ["a", "  b", "x", "d"];
+        let (left_changed, right_changed) = changed_lines(&left, &right);
+        assert_eq!(left_changed, vec![false, false, true, false]);
+        assert_eq!(right_changed, vec![false, false, true, false]);
+    }
+
+    #[test]
+    fn test_changed_lines_in_several_places() {
+        let left = ["a", "b", "c", "d", "e", "f"];
+        let right = ["x", "b", "c", "y", "e", "f", "z"];
+        let (left_changed, right_changed) = changed_lines(&left, &right);
+        assert_eq!(left_changed, vec![true, false, false, true, false, false]);
+        assert_eq!(
+            right_changed,
+            vec![true, false, false, true, false, false, true]
+        );
+        assert_eq!(changed_lines(&[], &["a"]), (vec![], vec![true]));
+    }
+
+    #[test]
+    fn test_to_html() {
+        let left = create_temp_file("int a;\nif (x < y) {\n  run();\n}\nint b;\n", "c");
+        let right = create_temp_file("if (x < y) {\n  stop();\n}\n", "c");
+        let left_path = left.to_string_lossy().to_string();
+        let right_path = right.to_string_lossy().to_string();
+        let reports = vec![DuplicateReport {
+            fingerprint: "0123456789abcdef".to_string(),
+            clone_type: CloneType::Parameterized,
+            line_count: 3,
+            similarity: None,
+            baseline_status: None,
+            blocks: vec![
+                block(&left_path, 2, 4),
+                block(&right_path, 1, 3),
+                block("missing/file.c", 1, 3),
+            ],
+        }];
+
+        let analyzed = [(&left_path, 5), (&right_path, 3)].map(|(path, lines)| AnalyzedFile {
+            path: path.clone(),
+            language: "c".to_string(),
+            lines,
+        });
+        let html = to_html(&reports, &Summary::new(&reports, &analyzed));
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(
+            html.contains("<div class=\"value\">1</div><div class=\"label\">Clone classes</div>")
+        );
+        assert!(html.contains(
+            "<div class=\"value\">3</div><div class=\"label\">Files with duplicates</div>"
+        ));
+        assert!(
+            html.contains("<div class=\"value\">8</div><div class=\"label\">Lines analyzed</div>")
+        );
+        // 3 of the 5 lines of the left file are duplicated
+        assert!(html.contains("data-value=\"60.00\">60.0%"));
+        assert!(html.contains("data-value=\"100.00\">100.0%"));
+        assert!(html.contains("<tr><td>missing/file.c</td><td class=\"number\">-</td>"));
+        assert!(html.contains("<h2>Languages</h2>"));
+        assert!(!html.contains("New clone classes"));
+        assert!(html.contains("<tr><td>tests/temp</td><td class=\"number\">8</td>"));
+        assert!(html.contains("if (x &lt; y) {"));
+        assert!(html
+            .contains("<span class=\"line changed\"><span class=\"ln\">3</span>  run();</span>"));
+        assert!(
+            html.contains("<span class=\"line\"><span class=\"ln\">1</span>if (x &lt; y) {</span>")
+        );
+        assert!(html.contains("Source not available"));
+        assert!(html.contains("<a href=\"#clone-1\">1</a></td>"));
+        assert!(html.contains("<td>parameterized</td>"));
+        assert!(html.contains("id=\"clone-1\"><summary>Clone class 1 &middot;"));
+        assert!(!html.contains("clone-0"));
+        assert!(!html.contains("<script src"));
+
+        delete_temp_file(&left);
+        delete_temp_file(&right);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::models::detection_types::DuplicateReport;
+use crate::models::report::Summary;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const SUITE_NAME: &str = "duplicate-code";
+
+#[derive(Serialize)]
+#[serde(rename = "testsuites")]
+struct TestSuites {
+    #[serde(rename = "@name")]
+    name: &'static str,
+    #[serde(rename = "@tests")]
+    tests: usize,
+    #[serde(rename = "@failures")]
+    failures: usize,
+    testsuite: TestSuite,
+}
+
+#[derive(Serialize)]
+struct TestSuite {
+    #[serde(rename = "@name")]
+    name: &'static str,
+    #[serde(rename = "@tests")]
+    tests: usize,
+    #[serde(rename = "@failures")]
+    failures: usize,
+    #[serde(rename = "@errors")]
+    errors: usize,
+    #[serde(rename = "@skipped")]
+    skipped: usize,
+    properties: Properties,
+    #[serde(rename = "testcase")]
+    testcases: Vec<TestCase>,
+}
+
+#[derive(Serialize)]
+struct Properties {
+    property: Vec<Property>,
+}
+
+#[derive(Serialize)]
+struct Property {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@value")]
+    value: String,
+}
+
+impl Property {
+    fn new(name: String, value: impl ToString) -> Self {
+        Property {
+            name,
+            value: value.to_string(),
+        }
+    }
+}
+
+/// Suite properties carrying the duplication totals and the per-language and
+/// per-directory breakdowns; per-file ratios go in the failure messages
+fn summary_properties(summary: &Summary) -> Properties {
+    let mut property = vec![
+        Property::new("totalLines".to_string(), summary.total_lines),
+        Property::new(
+            "uniqueDuplicateLines".to_string(),
+            summary.unique_duplicate_lines,
+        ),
+        Property::new("duplicationRatio".to_string(), summary.duplication_ratio),
+    ];
+    if let Some(baseline) = &summary.baseline {
+        property.push(Property::new("newClones".to_string(), baseline.new));
+        property.push(Property::new(
+            "existingClones".to_string(),
+            baseline.existing,
+        ));
+        property.push(Property::new(
+            "resolvedClones".to_string(),
+            baseline.resolved,
+        ));
+    }
+    for (prefix, breakdowns) in [
+        ("language", &summary.languages),
+        ("directory", &summary.directories),
+    ] {
+        property.extend(breakdowns.iter().map(|breakdown| {
+            Property::new(
+                format!("{}.{}.duplicationRatio", prefix, breakdown.name),
+                breakdown.duplication_ratio,
+            )
+        }));
+    }
+    Properties { property }
+}
+
+#[derive(Serialize)]
+struct TestCase {
+    #[serde(rename = "@classname")]
+    classname: &'static str,
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failure: Option<Failure>,
+}
+
+#[derive(Serialize)]
+struct Failure {
+    #[serde(rename = "@message")]
+    message: String,
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    #[serde(rename = "$text")]
+    details: String,
+}
+
+/// One failing line per duplicate block of a file, naming the blocks it duplicates
+#[derive(Default)]
+struct FileClones {
+    blocks: usize,
+    /// Sum of the block lengths, used when the summary does not list the file
+    lines: usize,
+    /// Lines covered by the file's blocks, counting nested blocks once
+    duplicated_lines: Option<usize>,
+    details: Vec<String>,
+    /// Share of the file's lines that are duplicated, when its line count is known
+    ratio: Option<f64>,
+}
+
+/// JUnit XML with one testcase per analyzed file, failing when the file takes part in a
+/// clone class
+pub fn to_junit(reports: &[DuplicateReport], summary: &Summary) -> Result<String, std::io::Error> {
+    let mut files: BTreeMap<&str, FileClones> = BTreeMap::new();
+    for file in &summary.files {
+        let clones = files.entry(file.name.as_str()).or_default();
+        clones.duplicated_lines = Some(file.duplicated_lines);
+        clones.ratio = Some(file.duplication_ratio).filter(|_| file.total_lines > 0);
+    }
+    for report in reports {
+        for (index, block) in report.blocks.iter().enumerate() {
+            let others: Vec<String> = report
+                .blocks
+                .iter()
+                .enumerate()
+                .filter(|(other_index, _)| *other_index != index)
+                .map(|(_, other)| {
+                    format!(
+                        "{}:{}-{}",
+                        other.source_file, other.start_line_number, other.end_line_number
+                    )
+                })
+                .collect();
+            let lines = block.end_line_number - block.start_line_number + 1;
+            let file = files.entry(block.source_file.as_str()).or_default();
+            file.blocks += 1;
+            file.lines += lines;
+            file.details.push(format!(
+                "Lines {}-{} ({} lines) duplicated in {} [{}]",
+                block.start_line_number,
+                block.end_line_number,
+                lines,
+                others.join(", "),
+                report.fingerprint
+            ));
+        }
+    }
+
+    let testcases: Vec<TestCase> = files
+        .into_iter()
+        .map(|(name, clones)| TestCase {
+            classname: SUITE_NAME,
+            name: name.to_string(),
+            failure: (clones.blocks > 0).then(|| {
+                let mut message = format!(
+                    "{} duplicated blocks covering {} lines",
+                    clones.blocks,
+                    clones.duplicated_lines.unwrap_or(clones.lines)
+                );
+                if let Some(ratio) = clones.ratio {
+                    message.push_str(&format!(" ({:.1}% of the file)", ratio * 100.0));
+                }
+                Failure {
+                    message,
+                    kind: SUITE_NAME,
+                    details: clones.details.join("\n"),
+                }
+            }),
+        })
+        .collect();
+    let failures = testcases
+        .iter()
+        .filter(|testcase| testcase.failure.is_some())
+        .count();
+
+    let suites = TestSuites {
+        name: "reson",
+        tests: testcases.len(),
+        failures,
+        testsuite: TestSuite {
+            name: SUITE_NAME,
+            tests: testcases.len(),
+            failures,
+            errors: 0,
+            skipped: 0,
+            properties: summary_properties(summary),
+            testcases,
+        },
+    };
+    let xml =
+        quick_xml::se::to_string(&suites).map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+        xml
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+    use crate::models::detection_types::DuplicateBlock;
+    use crate::models::report::{AnalyzedFile, BaselineSummary};
+
+    fn block(source_file: &str, start: usize, end: usize) -> DuplicateBlock {
+        DuplicateBlock {
+            start_line_number: start,
+            end_line_number: end,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        }
+    }
+
+    #[test]
+    fn test_to_junit() {
+        let reports = vec![
+            DuplicateReport {
+                fingerprint: "aa".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 5,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/a.c", 1, 5), block("src/b.c", 20, 24)],
+            },
+            DuplicateReport {
+                fingerprint: "bb".to_string(),
+                clone_type: CloneType::Exact,
+                line_count: 3,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![block("src/a.c", 10, 12), block("src/a.c", 30, 32)],
+            },
+            // A near-miss class whose blocks differ in length, one nested in "aa"
+            DuplicateReport {
+                fingerprint: "cc".to_string(),
+                clone_type: CloneType::Parameterized,
+                line_count: 4,
+                similarity: Some(0.9),
+                baseline_status: None,
+                blocks: vec![block("src/b.c", 21, 24), block("src/c.c", 1, 6)],
+            },
+        ];
+
+        let analyzed: Vec<AnalyzedFile> = [
+            ("src/a.c", 40),
+            ("src/b.c", 10),
+            ("src/c.c", 25),
+            ("src/d.c", 10),
+        ]
+        .iter()
+        .map(|(path, lines)| AnalyzedFile {
+            path: path.to_string(),
+            language: "c".to_string(),
+            lines: *lines,
+        })
+        .collect();
+        let summary = Summary::new(&reports, &analyzed);
+
+        let xml = to_junit(&reports, &summary).unwrap();
+        assert!(xml.contains("<testsuites name=\"reson\" tests=\"4\" failures=\"3\">"));
+        assert!(xml.contains("<testsuite name=\"duplicate-code\" tests=\"4\" failures=\"3\" errors=\"0\" skipped=\"0\"><properties>\
+             <property name=\"totalLines\" value=\"85\"/>\
+             <property name=\"uniqueDuplicateLines\" value=\"22\"/>"));
+        assert!(xml.contains(
+            "<property name=\"language.c.duplicationRatio\" value=\"0.25882352941176473\"/>"
+        ));
+        assert!(xml.contains("<property name=\"directory.src.duplicationRatio\""));
+
+        let summary = Summary {
+            baseline: Some(BaselineSummary {
+                new: 1,
+                existing: 1,
+                resolved: 0,
+            }),
+            ..summary
+        };
+        let xml = to_junit(&reports, &summary).unwrap();
+        assert!(xml.contains("<property name=\"newClones\" value=\"1\"/>"));
+        assert!(xml.contains(
+            "<testcase classname=\"duplicate-code\" name=\"src/a.c\">\
+             <failure message=\"3 duplicated blocks covering 11 lines (27.5% of the file)\" type=\"duplicate-code\">\
+             Lines 1-5 (5 lines) duplicated in src/b.c:20-24 [aa]\n\
+             Lines 10-12 (3 lines) duplicated in src/a.c:30-32 [bb]\n\
+             Lines 30-32 (3 lines) duplicated in src/a.c:10-12 [bb]\
+             </failure></testcase>"
+        ));
+        assert!(xml.contains(
+            "name=\"src/b.c\"><failure message=\"2 duplicated blocks covering 5 lines (50.0% of the file)\""
+        ));
+        assert!(xml.contains(
+            "name=\"src/c.c\"><failure message=\"1 duplicated blocks covering 6 lines (24.0% of the file)\" type=\"duplicate-code\">\
+             Lines 1-6 (6 lines) duplicated in src/b.c:21-24 [cc]\
+             </failure>"
+        ));
+        assert!(xml.contains("<testcase classname=\"duplicate-code\" name=\"src/d.c\"/>"));
+    }
+
+    #[test]
+    fn test_to_junit_without_clones() {
+        let xml = to_junit(&[], &Summary::default()).unwrap();
+        assert!(!xml.contains("newClones"));
+        assert!(xml.contains("<testsuites name=\"reson\" tests=\"0\" failures=\"0\">"));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::models::detection_types::{BaselineStatus, DuplicateReport};
+use crate::models::report::{most_duplicated, Breakdown, Summary};
+use std::fmt::Write;
+
+/// Number of clone classes listed in the table, largest first
+const TOP_CLASSES: usize = 20;
+
+/// Number of directories and files listed in their tables, most duplicated lines first
+const TOP_PATHS: usize = 10;
+
+/// Escape characters that would break a table cell or be read as inline markup
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '|' | '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Lines covered by all blocks of a clone class, used to rank classes
+fn duplicated_lines(report: &DuplicateReport) -> usize {
+    report.line_count * report.blocks.len()
+}
+
+fn write_breakdown_table(markdown: &mut String, heading: &str, column: &str, rows: &[&Breakdown]) {
+    if rows.is_empty() {
+        return;
+    }
+    let _ = writeln!(markdown, "\n### {}\n", heading);
+    let _ = writeln!(
+        markdown,
+        "| {} | Lines | Duplicated lines | Duplication |",
+        column
+    );
+    markdown.push_str("| --- | ---: | ---: | ---: |\n");
+    for row in rows {
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {:.1}% |",
+            escape(&row.name),
+            row.total_lines,
+            row.duplicated_lines,
+            row.duplication_ratio * 100.0
+        );
+    }
+}
+
+/// Summary tables followed by the largest clone classes, for pull request comments
+pub fn to_markdown(reports: &[DuplicateReport], summary: &Summary) -> String {
+    let mut markdown = String::from("## Code duplication\n\n");
+    markdown
+        .push_str("| Clone classes | Duplicate blocks | Duplicate lines | Files | Duplication |\n");
+    markdown.push_str("| ---: | ---: | ---: | ---: | ---: |\n");
+    let _ = writeln!(
+        markdown,
+        "| {} | {} | {} | {} | {:.1}% of {} lines |",
+        reports.len(),
+        summary.duplicate_blocks,
+        summary.duplicate_lines,
+        summary.duplicate_files,
+        summary.duplication_ratio * 100.0,
+        summary.total_lines,
+    );
+
+    if let Some(baseline) = &summary.baseline {
+        let _ = writeln!(
+            markdown,
+            "\nCompared with the baseline: **{} new**, {} existing and {} resolved clone classes.",
+            baseline.new, baseline.existing, baseline.resolved
+        );
+    }
+    if summary.suppressed_blocks > 0 {
+        let _ = writeln!(
+            markdown,
+            "\n{} duplicate blocks are suppressed by `reson-ignore` comments.",
+            summary.suppressed_blocks
+        );
+    }
+
+    let languages: Vec<&Breakdown> = summary.languages.iter().collect();
+    write_breakdown_table(
+        &mut markdown,
+        "Duplication by language",
+        "Language",
+        &languages,
+    );
+    write_breakdown_table(
+        &mut markdown,
+        "Most duplicated directories",
+        "Directory",
+        &most_duplicated(&summary.directories, TOP_PATHS),
+    );
+    write_breakdown_table(
+        &mut markdown,
+        "Most duplicated files",
+        "File",
+        &most_duplicated(&summary.files, TOP_PATHS),
+    );
+
+    if reports.is_empty() {
+        markdown.push_str("\nNo duplicated code found.\n");
+        return markdown;
+    }
+
+    // Keep the class ids of the other formats, which number classes in report order
+    let mut ranked: Vec<(usize, &DuplicateReport)> = reports.iter().enumerate().collect();
+    ranked.sort_by_key(|(_, report)| std::cmp::Reverse(duplicated_lines(report)));
+
+    let shown = ranked.len().min(TOP_CLASSES);
+    let _ = writeln!(markdown, "\n### Top {} clone classes\n", shown);
+    markdown.push_str("| Class | Lines | Blocks | Type | Locations |\n");
+    markdown.push_str("| ---: | ---: | ---: | --- | --- |\n");
+    for (index, report) in ranked.iter().take(shown) {
+        let locations: Vec<String> = report
+            .blocks
+            .iter()
+            .map(|block| {
+                format!(
+                    "{}:{}-{}",
+                    escape(&block.source_file),
+                    block.start_line_number,
+                    block.end_line_number
+                )
+            })
+            .collect();
+        let mut clone_type = report.clone_type.as_str().to_string();
+        if report.baseline_status == Some(BaselineStatus::New) {
+            clone_type.push_str(", **new**");
+        }
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {} | {} |",
+            index + 1,
+            report.line_count,
+            report.blocks.len(),
+            clone_type,
+            locations.join("<br>")
+        );
+    }
+    if ranked.len() > shown {
+        let _ = writeln!(
+            markdown,
+            "\n_{} more clone classes not shown._",
+            ranked.len() - shown
+        );
+    }
+
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+    use crate::models::detection_types::DuplicateBlock;
+    use crate::models::report::{AnalyzedFile, BaselineSummary};
+
+    fn report(line_count: usize, files: &[&str]) -> DuplicateReport {
+        DuplicateReport {
+            fingerprint: format!("fp{}", line_count),
+            clone_type: CloneType::Parameterized,
+            line_count,
+            similarity: None,
+            baseline_status: None,
+            blocks: files
+                .iter()
+                .map(|file| DuplicateBlock {
+                    start_line_number: 1,
+                    end_line_number: line_count,
+                    source_file: file.to_string(),
+                    symbol_name: None,
+                    symbol_kind: None,
+                    context: None,
+                    snippet: None,
+                    byte_range: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("src/my_file|x.c"), "src/my\\_file\\|x.c");
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let reports = vec![report(5, &["a.c", "b.c"]), report(8, &["c.c", "d.c"])];
+        let analyzed: Vec<AnalyzedFile> = ["a.c", "b.c", "c.c", "d.c", "lib/e.c"]
+            .iter()
+            .map(|path| AnalyzedFile {
+                path: path.to_string(),
+                language: "c".to_string(),
+                lines: 20,
+            })
+            .collect();
+        let summary = Summary::new(&reports, &analyzed);
+
+        let markdown = to_markdown(&reports, &summary);
+        assert!(markdown.contains("| 2 | 4 | 26 | 4 | 26.0% of 100 lines |\n"));
+        assert!(markdown.contains(
+            "### Duplication by language\n\n\
+             | Language | Lines | Duplicated lines | Duplication |\n\
+             | --- | ---: | ---: | ---: |\n\
+             | c | 100 | 26 | 26.0% |\n"
+        ));
+        assert!(markdown.contains("| Directory | Lines | Duplicated lines | Duplication |"));
+        assert!(!markdown.contains("| lib |"));
+        assert!(markdown.contains("| c.c | 20 | 8 | 40.0% |\n| d.c | 20 | 8 | 40.0% |\n"));
+        assert!(markdown.contains("### Top 2 clone classes"));
+        let largest = markdown.find("| 2 | 8 | 2 | parameterized | c.c:1-8<br>d.c:1-8 |");
+        let smallest = markdown.find("| 1 | 5 | 2 | parameterized | a.c:1-5<br>b.c:1-5 |");
+        assert!(largest.unwrap() < smallest.unwrap());
+        assert!(!markdown.contains("not shown"));
+    }
+
+    #[test]
+    fn test_to_markdown_limits_classes() {
+        let reports: Vec<DuplicateReport> = (1..=TOP_CLASSES + 3)
+            .map(|lines| report(lines, &["a.c", "b.c"]))
+            .collect();
+        let markdown = to_markdown(&reports, &Summary::default());
+        assert!(markdown.contains(&format!("### Top {} clone classes", TOP_CLASSES)));
+        assert!(markdown.contains("_3 more clone classes not shown._"));
+
+        let markdown = to_markdown(&[], &Summary::default());
+        assert!(markdown.contains("No duplicated code found."));
+        assert!(!markdown.contains("baseline"));
+    }
+
+    #[test]
+    fn test_to_markdown_baseline() {
+        let mut reports = vec![report(5, &["a.c", "b.c"]), report(8, &["c.c", "d.c"])];
+        reports[0].baseline_status = Some(BaselineStatus::New);
+        reports[1].baseline_status = Some(BaselineStatus::Existing);
+        let summary = Summary {
+            baseline: Some(BaselineSummary::new(&reports, &[])),
+            ..Summary::default()
+        };
+
+        let markdown = to_markdown(&reports, &summary);
+        assert!(markdown.contains(
+            "Compared with the baseline: **1 new**, 1 existing and 0 resolved clone classes."
+        ));
+        assert!(markdown.contains("| 1 | 5 | 2 | parameterized, **new** | a.c:1-5<br>b.c:1-5 |"));
+        assert!(markdown.contains("| 2 | 8 | 2 | parameterized | c.c:1-8<br>d.c:1-8 |"));
+        assert!(!markdown.contains("suppressed"));
+
+        let summary = Summary {
+            suppressed_blocks: 2,
+            ..Summary::default()
+        };
+        assert!(to_markdown(&reports, &summary)
+            .contains("\n2 duplicate blocks are suppressed by `reson-ignore` comments.\n"));
+    }
+}
@@ -0,0 +1,322 @@
+use super::block_label;
+use crate::models::detection_types::{BaselineStatus, DuplicateBlock, DuplicateReport};
+use crate::models::report::Summary;
+use serde_json::{json, Value};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const RULE_ID: &str = "duplicate-code";
+
+/// Turn a reported file path into a URI reference: relative paths stay relative,
+/// absolute ones become `file://` URIs
+fn path_to_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.strip_prefix("./").unwrap_or(&path);
+    let mut uri = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    if uri.starts_with('/') {
+        format!("file://{}", uri)
+    } else if uri.as_bytes().get(1) == Some(&b':') {
+        // Windows drive letter
+        format!("file:///{}", uri)
+    } else {
+        uri
+    }
+}
+
+fn physical_location(block: &DuplicateBlock) -> Value {
+    let mut region = json!({
+        "startLine": block.start_line_number,
+        "endLine": block.end_line_number,
+    });
+    if let Some(snippet) = &block.snippet {
+        region["startColumn"] = json!(snippet.start_column);
+        region["endColumn"] = json!(snippet.end_column);
+        region["byteOffset"] = json!(snippet.start_byte);
+        region["byteLength"] = json!(snippet.end_byte - snippet.start_byte);
+        if !snippet.truncated {
+            region["snippet"] = json!({ "text": snippet.text });
+        }
+    }
+
+    json!({
+        "artifactLocation": { "uri": path_to_uri(&block.source_file) },
+        "region": region,
+    })
+}
+
+/// One SARIF result per clone class: the first block is the primary location and its
+/// siblings are linked from the message through `relatedLocations`
+fn clone_class_result(report: &DuplicateReport) -> Option<Value> {
+    let (first, siblings) = report.blocks.split_first()?;
+
+    let links: Vec<String> = siblings
+        .iter()
+        .enumerate()
+        .map(|(index, block)| format!("[{}]({})", block_label(block), index + 1))
+        .collect();
+    let mut message = format!(
+        "Duplicated code: {} lines repeated in {} locations.",
+        report.line_count,
+        report.blocks.len()
+    );
+    if let Some(similarity) = report.similarity {
+        message.push_str(&format!(" Similarity: {:.0}%.", similarity * 100.0));
+    }
+    if !links.is_empty() {
+        message.push_str(&format!(" Also at {}.", links.join(", ")));
+    }
+
+    let related_locations: Vec<Value> = siblings
+        .iter()
+        .enumerate()
+        .map(|(index, block)| {
+            json!({
+                "id": index + 1,
+                "physicalLocation": physical_location(block),
+                "message": { "text": block_label(block) },
+            })
+        })
+        .collect();
+
+    let mut properties = json!({
+        "cloneType": report.clone_type,
+        "lineCount": report.line_count,
+        "blockCount": report.blocks.len(),
+    });
+    if let Some(similarity) = report.similarity {
+        properties["similarity"] = json!(similarity);
+    }
+
+    let mut result = json!({
+        "ruleId": RULE_ID,
+        "ruleIndex": 0,
+        "level": "warning",
+        "message": { "text": message },
+        "locations": [{ "physicalLocation": physical_location(first) }],
+        "relatedLocations": related_locations,
+        "partialFingerprints": { "resonCloneClass/v1": report.fingerprint },
+        "properties": properties,
+    });
+    if let Some(status) = report.baseline_status {
+        result["baselineState"] = json!(baseline_state(status));
+    }
+    Some(result)
+}
+
+/// The SARIF `baselineState` matching a clone class's status against the baseline
+fn baseline_state(status: BaselineStatus) -> &'static str {
+    match status {
+        BaselineStatus::New => "new",
+        BaselineStatus::Existing => "unchanged",
+        BaselineStatus::Resolved => "absent",
+    }
+}
+
+/// Build a SARIF 2.1.0 log with a single run holding one result per clone class
+///
+/// Resolved baseline classes follow as `absent` results, and blocks left out by
+/// `reson-ignore` comments as results with an `inSource` suppression. The summary,
+/// with its per-file, per-directory and per-language breakdowns, goes in the run's
+/// property bag.
+pub fn to_sarif(
+    reports: &[DuplicateReport],
+    resolved: &[DuplicateReport],
+    suppressed: &[DuplicateReport],
+    summary: &Summary,
+) -> Value {
+    let mut results: Vec<Value> = reports
+        .iter()
+        .chain(resolved)
+        .filter_map(clone_class_result)
+        .collect();
+    results.extend(
+        suppressed
+            .iter()
+            .filter_map(clone_class_result)
+            .map(|mut result| {
+                result["suppressions"] = json!([{
+                    "kind": "inSource",
+                    "justification": "reson-ignore comment",
+                }]);
+                result
+            }),
+    );
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "reson",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/nexepic/reson",
+                    "rules": [{
+                        "id": RULE_ID,
+                        "name": "DuplicateCode",
+                        "shortDescription": { "text": "Duplicated code" },
+                        "fullDescription": {
+                            "text": "A block of code is repeated in several places and is a candidate for refactoring."
+                        },
+                        "defaultConfiguration": { "level": "warning" },
+                    }],
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+            "properties": { "summary": summary },
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+
+    fn block(source_file: &str, start: usize, end: usize) -> DuplicateBlock {
+        DuplicateBlock {
+            start_line_number: start,
+            end_line_number: end,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range: None,
+        }
+    }
+
+    #[test]
+    fn test_path_to_uri() {
+        assert_eq!(path_to_uri("./src/main.rs"), "src/main.rs");
+        assert_eq!(path_to_uri("src\\my file.rs"), "src/my%20file.rs");
+        assert_eq!(path_to_uri("/tmp/a.rs"), "file:///tmp/a.rs");
+        assert_eq!(path_to_uri("C:\\code\\a.rs"), "file:///C:/code/a.rs");
+    }
+
+    #[test]
+    fn test_to_sarif() {
+        let reports = vec![
+            DuplicateReport {
+                fingerprint: "abc".to_string(),
+                clone_type: CloneType::Parameterized,
+                line_count: 12,
+                similarity: None,
+                baseline_status: None,
+                blocks: vec![
+                    block("./src/a.rs", 1, 12),
+                    block("./src/b.rs", 20, 31),
+                    block("./src/c.rs", 5, 16),
+                ],
+            },
+            DuplicateReport {
+                fingerprint: "def".to_string(),
+                clone_type: CloneType::Parameterized,
+                line_count: 8,
+                similarity: Some(0.875),
+                baseline_status: None,
+                blocks: vec![block("./src/a.rs", 40, 47), block("./src/d.rs", 3, 9)],
+            },
+        ];
+
+        let log = to_sarif(&reports, &[], &[], &Summary::default());
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(log["$schema"], SARIF_SCHEMA);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "reson");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], RULE_ID);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        let first = &results[0];
+        assert_eq!(first["ruleId"], RULE_ID);
+        assert_eq!(
+            first["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "src/a.rs"
+        );
+        assert_eq!(
+            first["locations"][0]["physicalLocation"]["region"],
+            json!({ "startLine": 1, "endLine": 12 })
+        );
+        let related = first["relatedLocations"].as_array().unwrap();
+        assert_eq!(related.len(), 2);
+        assert_eq!(related[1]["id"], 2);
+        assert_eq!(
+            related[1]["physicalLocation"]["artifactLocation"]["uri"],
+            "src/c.rs"
+        );
+        assert!(first["message"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("[./src/c.rs:5-16](2)"));
+        assert_eq!(first["partialFingerprints"]["resonCloneClass/v1"], "abc");
+
+        assert_eq!(results[1]["properties"]["similarity"], 0.875);
+        assert!(results[1]["message"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Similarity: 88%"));
+        assert!(results[1].get("baselineState").is_none());
+    }
+
+    #[test]
+    fn test_to_sarif_baseline_state() {
+        let report = |fingerprint: &str, status| DuplicateReport {
+            fingerprint: fingerprint.to_string(),
+            clone_type: CloneType::Exact,
+            line_count: 5,
+            similarity: None,
+            baseline_status: Some(status),
+            blocks: vec![block("src/a.rs", 1, 5), block("src/b.rs", 1, 5)],
+        };
+        let reports = vec![
+            report("aa", BaselineStatus::New),
+            report("bb", BaselineStatus::Existing),
+        ];
+        let resolved = vec![report("cc", BaselineStatus::Resolved)];
+
+        let log = to_sarif(&reports, &resolved, &[], &Summary::default());
+        let states: Vec<&Value> = log["runs"][0]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| &result["baselineState"])
+            .collect();
+        assert_eq!(states, vec!["new", "unchanged", "absent"]);
+    }
+
+    #[test]
+    fn test_to_sarif_suppressed() {
+        let report = DuplicateReport {
+            fingerprint: "aa".to_string(),
+            clone_type: CloneType::Exact,
+            line_count: 5,
+            similarity: None,
+            baseline_status: None,
+            blocks: vec![block("src/a.rs", 1, 5), block("src/b.rs", 1, 5)],
+        };
+        let suppressed = vec![DuplicateReport {
+            blocks: vec![block("src/c.rs", 1, 5)],
+            ..report.clone()
+        }];
+
+        let log = to_sarif(&[report], &[], &suppressed, &Summary::default());
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].get("suppressions").is_none());
+        assert_eq!(results[1]["suppressions"][0]["kind"], "inSource");
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "src/c.rs"
+        );
+    }
+}
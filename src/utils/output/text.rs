@@ -0,0 +1,312 @@
+use crate::models::detection_types::{BaselineStatus, DuplicateBlock, DuplicateReport};
+use crate::models::report::{most_duplicated, Summary};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+/// Number of source lines shown under each clone class
+const EXCERPT_LINES: usize = 6;
+
+/// Number of directories listed in the summary, most duplicated lines first
+const TOP_DIRECTORIES: usize = 5;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const CYAN: &str = "\x1b[36m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Wraps text in ANSI styles only when color is enabled
+struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// The first lines of a block, from its snippet when present, otherwise from its file
+fn excerpt(block: &DuplicateBlock, sources: &mut HashMap<String, Option<String>>) -> Vec<String> {
+    if let Some(snippet) = &block.snippet {
+        return snippet
+            .text
+            .lines()
+            .take(EXCERPT_LINES)
+            .map(str::to_string)
+            .collect();
+    }
+
+    let source = sources
+        .entry(block.source_file.clone())
+        .or_insert_with(|| fs::read_to_string(&block.source_file).ok());
+    source
+        .as_deref()
+        .map(|source| {
+            source
+                .lines()
+                .skip(block.start_line_number.saturating_sub(1))
+                .take(EXCERPT_LINES.min(block.end_line_number + 1 - block.start_line_number))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_summary(
+    text: &mut String,
+    summary: &Summary,
+    reports: &[DuplicateReport],
+    painter: &Painter,
+) {
+    let _ = writeln!(
+        text,
+        "{} {} clone classes, {} duplicate blocks, {} duplicate lines in {} files",
+        painter.paint(BOLD, "Summary:"),
+        reports.len(),
+        summary.duplicate_blocks,
+        summary.duplicate_lines,
+        summary.duplicate_files,
+    );
+    let _ = writeln!(
+        text,
+        "{} {:.1}% of {} lines ({} lines)",
+        painter.paint(BOLD, "Duplication:"),
+        summary.duplication_ratio * 100.0,
+        summary.total_lines,
+        summary.unique_duplicate_lines,
+    );
+    if let Some(baseline) = &summary.baseline {
+        let _ = writeln!(
+            text,
+            "{} {} new, {} existing, {} resolved clone classes",
+            painter.paint(BOLD, "Baseline:"),
+            baseline.new,
+            baseline.existing,
+            baseline.resolved,
+        );
+    }
+    if summary.suppressed_blocks > 0 {
+        let _ = writeln!(
+            text,
+            "{} {} duplicate blocks by reson-ignore comments",
+            painter.paint(BOLD, "Suppressed:"),
+            summary.suppressed_blocks,
+        );
+    }
+    if !summary.languages.is_empty() {
+        let languages: Vec<String> = summary
+            .languages
+            .iter()
+            .map(|language| {
+                format!(
+                    "{} {:.1}%",
+                    language.name,
+                    language.duplication_ratio * 100.0
+                )
+            })
+            .collect();
+        let _ = writeln!(
+            text,
+            "{} {}",
+            painter.paint(BOLD, "Languages:"),
+            languages.join(", ")
+        );
+    }
+    let directories = most_duplicated(&summary.directories, TOP_DIRECTORIES);
+    if !directories.is_empty() {
+        let _ = writeln!(
+            text,
+            "{}",
+            painter.paint(BOLD, "Most duplicated directories:")
+        );
+        for directory in directories {
+            let _ = writeln!(
+                text,
+                "  {}  {} of {} lines ({:.1}%)",
+                painter.paint(CYAN, &directory.name),
+                directory.duplicated_lines,
+                directory.total_lines,
+                directory.duplication_ratio * 100.0
+            );
+        }
+    }
+}
+
+/// Render clone classes for reading in a terminal, followed by the summary
+///
+/// Locations are printed as `file:line` so terminals and editors can link them.
+/// Color is only used when requested, typically when stdout is a TTY.
+pub fn to_text(reports: &[DuplicateReport], summary: &Summary, color: bool) -> String {
+    let painter = Painter { color };
+    let mut sources = HashMap::new();
+    let mut text = String::new();
+
+    for (index, report) in reports.iter().enumerate() {
+        let mut header = format!(
+            "Clone class {} · {} lines · {} blocks · {}",
+            index + 1,
+            report.line_count,
+            report.blocks.len(),
+            report.clone_type.as_str()
+        );
+        if let Some(similarity) = report.similarity {
+            header.push_str(&format!(" · {:.0}% similar", similarity * 100.0));
+        }
+        if report.baseline_status == Some(BaselineStatus::New) {
+            header.push_str(" · new");
+        }
+        let _ = writeln!(
+            text,
+            "{}",
+            painter.paint(&format!("{}{}", BOLD, YELLOW), &header)
+        );
+
+        for block in &report.blocks {
+            let location = format!(
+                "{}:{}-{}",
+                block.source_file, block.start_line_number, block.end_line_number
+            );
+            let _ = write!(text, "  {}", painter.paint(CYAN, &location));
+            if let Some(context) = &block.context {
+                let _ = write!(text, "  {}", painter.paint(DIM, context));
+            }
+            text.push('\n');
+        }
+
+        if let Some(first) = report.blocks.first() {
+            let lines = excerpt(first, &mut sources);
+            let last_line = first.start_line_number + lines.len().saturating_sub(1);
+            let width = last_line.to_string().len();
+            for (offset, line) in lines.iter().enumerate() {
+                let gutter = format!("{:>width$} │", first.start_line_number + offset);
+                let _ = writeln!(
+                    text,
+                    "    {} {}",
+                    painter.paint(DIM, &gutter),
+                    painter.paint(GREEN, line)
+                );
+            }
+            let remaining = report.line_count.saturating_sub(lines.len());
+            if !lines.is_empty() && remaining > 0 {
+                let _ = writeln!(
+                    text,
+                    "    {}",
+                    painter.paint(DIM, &format!("… {} more lines", remaining))
+                );
+            }
+        }
+        text.push('\n');
+    }
+
+    write_summary(&mut text, summary, reports, &painter);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+    use crate::models::report::{AnalyzedFile, BaselineSummary};
+    use crate::utils::files::{create_temp_file, delete_temp_file};
+
+    fn report(source_file: &str) -> DuplicateReport {
+        let block = |start, end, context: Option<&str>| DuplicateBlock {
+            start_line_number: start,
+            end_line_number: end,
+            source_file: source_file.to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: context.map(str::to_string),
+            snippet: None,
+            byte_range: None,
+        };
+        DuplicateReport {
+            fingerprint: "abc".to_string(),
+            clone_type: CloneType::Renamed,
+            line_count: 8,
+            similarity: Some(0.9),
+            baseline_status: None,
+            blocks: vec![block(2, 9, Some("Queue::push")), block(12, 19, None)],
+        }
+    }
+
+    #[test]
+    fn test_to_text_plain() {
+        let content: String = (1..=20).map(|line| format!("line {}\n", line)).collect();
+        let file_path = create_temp_file(&content, "c");
+        let path = file_path.to_string_lossy().to_string();
+        let records = [report(&path)];
+        let analyzed = [AnalyzedFile {
+            path: path.clone(),
+            language: "c".to_string(),
+            lines: 20,
+        }];
+        let summary = Summary::new(&records, &analyzed);
+
+        let text = to_text(&records, &summary, false);
+        assert!(!text.contains('\x1b'));
+        assert!(text.contains("Clone class 1 · 8 lines · 2 blocks · renamed · 90% similar"));
+        assert!(text.contains(&format!("  {}:2-9  Queue::push\n", path)));
+        assert!(text.contains(&format!("  {}:12-19\n", path)));
+        assert!(text.contains("\n    2 │ line 2\n"));
+        assert!(text.contains("\n    7 │ line 7\n"));
+        assert!(!text.contains("line 8\n"));
+        assert!(text.contains("… 2 more lines"));
+        assert!(text.contains(
+            "Summary: 1 clone classes, 2 duplicate blocks, 16 duplicate lines in 1 files\n\
+             Duplication: 80.0% of 20 lines (16 lines)\n\
+             Languages: c 80.0%\n\
+             Most duplicated directories:\n"
+        ));
+        assert!(text.ends_with("  tests/temp  16 of 20 lines (80.0%)\n"));
+
+        delete_temp_file(&file_path);
+    }
+
+    #[test]
+    fn test_to_text_baseline() {
+        let mut new = report("missing/file.c");
+        new.baseline_status = Some(BaselineStatus::New);
+        let mut existing = report("missing/other.c");
+        existing.baseline_status = Some(BaselineStatus::Existing);
+        let records = [new, existing];
+        let summary = Summary {
+            baseline: Some(BaselineSummary::new(&records, &[])),
+            ..Summary::default()
+        };
+
+        let text = to_text(&records, &summary, false);
+        assert!(text.contains("Clone class 1 · 8 lines · 2 blocks · renamed · 90% similar · new\n"));
+        assert!(text.contains("Clone class 2 · 8 lines · 2 blocks · renamed · 90% similar\n"));
+        assert!(text.contains("Baseline: 1 new, 1 existing, 0 resolved clone classes\n"));
+        assert!(!text.contains("Suppressed:"));
+    }
+
+    #[test]
+    fn test_to_text_suppressed() {
+        let summary = Summary {
+            suppressed_blocks: 3,
+            ..Summary::default()
+        };
+        let text = to_text(&[], &summary, false);
+        assert!(text.contains("Suppressed: 3 duplicate blocks by reson-ignore comments\n"));
+    }
+
+    #[test]
+    fn test_to_text_colored() {
+        let summary = Summary::default();
+        let text = to_text(&[report("missing/file.c")], &summary, true);
+        assert!(text.contains(&format!("{}missing/file.c:2-9{}", CYAN, RESET)));
+        assert!(text.contains(&format!("{}Summary:{}", BOLD, RESET)));
+        assert!(!text.contains("Baseline:"));
+        // Without a readable source there is no excerpt
+        assert!(!text.contains('│'));
+    }
+}
@@ -0,0 +1,402 @@
+use crate::models::code_types::CloneType;
+use crate::models::detection_types::{BaselineStatus, DuplicateBlock, DuplicateReport, Snippet};
+use crate::models::report::{Breakdown, Summary};
+use serde::{Deserialize, Serialize};
+
+/// Version of the element layout described by `schemas/reson-duplication.xsd`
+const SCHEMA_VERSION: &str = "1";
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename = "duplication")]
+pub struct XmlDuplication {
+    #[serde(rename = "@version")]
+    pub version: String,
+    pub summary: XmlSummary,
+    #[serde(rename = "clone", default)]
+    pub clones: Vec<XmlClone>,
+    /// Clone classes of the baseline report that were not found again
+    #[serde(rename = "resolved", default)]
+    pub resolved: Vec<XmlClone>,
+    /// Blocks left out by `reson-ignore` comments, grouped by clone class
+    #[serde(rename = "suppressed", default)]
+    pub suppressed: Vec<XmlClone>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct XmlSummary {
+    #[serde(rename = "@clones")]
+    pub clones: usize,
+    #[serde(rename = "@blocks")]
+    pub blocks: usize,
+    #[serde(rename = "@lines")]
+    pub lines: usize,
+    #[serde(rename = "@files")]
+    pub files: usize,
+    #[serde(rename = "@total-lines")]
+    pub total_lines: usize,
+    #[serde(rename = "@unique-lines")]
+    pub unique_lines: usize,
+    #[serde(rename = "@ratio")]
+    pub ratio: f64,
+    #[serde(rename = "@new", default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<usize>,
+    #[serde(rename = "@existing", default, skip_serializing_if = "Option::is_none")]
+    pub existing: Option<usize>,
+    #[serde(rename = "@resolved", default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<usize>,
+    #[serde(
+        rename = "@suppressed",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub suppressed: Option<usize>,
+    #[serde(rename = "file", default)]
+    pub file_breakdown: Vec<XmlBreakdown>,
+    #[serde(rename = "directory", default)]
+    pub directory_breakdown: Vec<XmlBreakdown>,
+    #[serde(rename = "language", default)]
+    pub language_breakdown: Vec<XmlBreakdown>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct XmlBreakdown {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@total-lines")]
+    pub total_lines: usize,
+    #[serde(rename = "@duplicated-lines")]
+    pub duplicated_lines: usize,
+    #[serde(rename = "@ratio")]
+    pub ratio: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct XmlClone {
+    #[serde(rename = "@fingerprint")]
+    pub fingerprint: String,
+    #[serde(rename = "@type")]
+    pub clone_type: CloneType,
+    #[serde(rename = "@lines")]
+    pub lines: usize,
+    #[serde(
+        rename = "@similarity",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub similarity: Option<f64>,
+    #[serde(rename = "@status", default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<BaselineStatus>,
+    #[serde(rename = "block", default)]
+    pub blocks: Vec<XmlBlock>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct XmlBlock {
+    #[serde(rename = "@file")]
+    pub file: String,
+    #[serde(rename = "@start")]
+    pub start: usize,
+    #[serde(rename = "@end")]
+    pub end: usize,
+    #[serde(rename = "@symbol", default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(
+        rename = "@symbol-kind",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub symbol_kind: Option<String>,
+    #[serde(rename = "@context", default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<XmlSnippet>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct XmlSnippet {
+    #[serde(rename = "@start-byte")]
+    pub start_byte: usize,
+    #[serde(rename = "@end-byte")]
+    pub end_byte: usize,
+    #[serde(rename = "@start-column")]
+    pub start_column: usize,
+    #[serde(rename = "@end-column")]
+    pub end_column: usize,
+    #[serde(rename = "@truncated")]
+    pub truncated: bool,
+    #[serde(rename = "$text", default)]
+    pub text: String,
+}
+
+impl From<&Breakdown> for XmlBreakdown {
+    fn from(breakdown: &Breakdown) -> Self {
+        XmlBreakdown {
+            name: breakdown.name.clone(),
+            total_lines: breakdown.total_lines,
+            duplicated_lines: breakdown.duplicated_lines,
+            ratio: breakdown.duplication_ratio,
+        }
+    }
+}
+
+impl From<&Snippet> for XmlSnippet {
+    fn from(snippet: &Snippet) -> Self {
+        XmlSnippet {
+            start_byte: snippet.start_byte,
+            end_byte: snippet.end_byte,
+            start_column: snippet.start_column,
+            end_column: snippet.end_column,
+            truncated: snippet.truncated,
+            text: snippet.text.clone(),
+        }
+    }
+}
+
+impl From<&DuplicateBlock> for XmlBlock {
+    fn from(block: &DuplicateBlock) -> Self {
+        XmlBlock {
+            file: block.source_file.clone(),
+            start: block.start_line_number,
+            end: block.end_line_number,
+            symbol: block.symbol_name.clone(),
+            symbol_kind: block.symbol_kind.clone(),
+            context: block.context.clone(),
+            snippet: block.snippet.as_ref().map(XmlSnippet::from),
+        }
+    }
+}
+
+impl From<&DuplicateReport> for XmlClone {
+    fn from(report: &DuplicateReport) -> Self {
+        XmlClone {
+            fingerprint: report.fingerprint.clone(),
+            clone_type: report.clone_type,
+            lines: report.line_count,
+            similarity: report.similarity,
+            status: report.baseline_status,
+            blocks: report.blocks.iter().map(XmlBlock::from).collect(),
+        }
+    }
+}
+
+impl XmlDuplication {
+    pub fn new(
+        reports: &[DuplicateReport],
+        resolved: &[DuplicateReport],
+        suppressed: &[DuplicateReport],
+        summary: &Summary,
+    ) -> Self {
+        let baseline = summary.baseline.as_ref();
+        XmlDuplication {
+            version: SCHEMA_VERSION.to_string(),
+            summary: XmlSummary {
+                clones: reports.len(),
+                blocks: summary.duplicate_blocks,
+                lines: summary.duplicate_lines,
+                files: summary.duplicate_files,
+                total_lines: summary.total_lines,
+                unique_lines: summary.unique_duplicate_lines,
+                ratio: summary.duplication_ratio,
+                new: baseline.map(|baseline| baseline.new),
+                existing: baseline.map(|baseline| baseline.existing),
+                resolved: baseline.map(|baseline| baseline.resolved),
+                suppressed: (summary.suppressed_blocks > 0).then_some(summary.suppressed_blocks),
+                file_breakdown: summary.files.iter().map(XmlBreakdown::from).collect(),
+                directory_breakdown: summary.directories.iter().map(XmlBreakdown::from).collect(),
+                language_breakdown: summary.languages.iter().map(XmlBreakdown::from).collect(),
+            },
+            clones: reports.iter().map(XmlClone::from).collect(),
+            resolved: resolved.iter().map(XmlClone::from).collect(),
+            suppressed: suppressed.iter().map(XmlClone::from).collect(),
+        }
+    }
+}
+
+/// The typed XML report described by `schemas/reson-duplication.xsd`
+pub fn to_xml(
+    reports: &[DuplicateReport],
+    resolved: &[DuplicateReport],
+    suppressed: &[DuplicateReport],
+    summary: &Summary,
+) -> Result<String, std::io::Error> {
+    let xml =
+        quick_xml::se::to_string(&XmlDuplication::new(reports, resolved, suppressed, summary))
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+        xml
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::report::{AnalyzedFile, BaselineSummary};
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    const SCHEMA: &str = include_str!("../../../schemas/reson-duplication.xsd");
+
+    fn reports() -> Vec<DuplicateReport> {
+        vec![DuplicateReport {
+            fingerprint: "f40bd297".to_string(),
+            clone_type: CloneType::Renamed,
+            line_count: 3,
+            similarity: Some(0.875),
+            baseline_status: None,
+            blocks: vec![
+                DuplicateBlock {
+                    start_line_number: 10,
+                    end_line_number: 12,
+                    source_file: "src/a&b.c".to_string(),
+                    symbol_name: Some("init_queue".to_string()),
+                    symbol_kind: Some("function".to_string()),
+                    context: Some("queue::init_queue".to_string()),
+                    snippet: Some(Snippet {
+                        start_byte: 120,
+                        end_byte: 160,
+                        start_column: 1,
+                        end_column: 2,
+                        text: "void init_queue(queue_t *q) {\n    q->head = 0;\n}".to_string(),
+                        truncated: false,
+                    }),
+                    byte_range: Some((120, 160)),
+                },
+                DuplicateBlock {
+                    start_line_number: 30,
+                    end_line_number: 32,
+                    source_file: "src/c.c".to_string(),
+                    symbol_name: None,
+                    symbol_kind: None,
+                    context: None,
+                    snippet: None,
+                    byte_range: None,
+                },
+            ],
+        }]
+    }
+
+    fn summary() -> Summary {
+        let analyzed: Vec<AnalyzedFile> = [("src/a&b.c", 40), ("src/c.c", 60)]
+            .iter()
+            .map(|(path, lines)| AnalyzedFile {
+                path: path.to_string(),
+                language: "c".to_string(),
+                lines: *lines,
+            })
+            .collect();
+        Summary::new(&reports(), &analyzed)
+    }
+
+    #[test]
+    fn test_to_xml_layout() {
+        let xml = to_xml(&reports(), &[], &[], &summary()).unwrap();
+
+        assert!(xml.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<duplication version=\"1\">"
+        ));
+        assert!(xml.contains("<summary clones=\"1\" blocks=\"2\" lines=\"6\" files=\"2\" total-lines=\"100\" unique-lines=\"6\" ratio=\"0.06\">"));
+        assert!(xml.contains(
+            "<file name=\"src/c.c\" total-lines=\"60\" duplicated-lines=\"3\" ratio=\"0.05\"/>"
+        ));
+        assert!(xml.contains(
+            "<directory name=\"src\" total-lines=\"100\" duplicated-lines=\"6\" ratio=\"0.06\"/>"
+        ));
+        assert!(xml.contains("<language name=\"c\" total-lines=\"100\" duplicated-lines=\"6\" ratio=\"0.06\"/></summary>"));
+        assert!(xml.contains(
+            "<clone fingerprint=\"f40bd297\" type=\"renamed\" lines=\"3\" similarity=\"0.875\">"
+        ));
+        assert!(xml.contains("<block file=\"src/a&amp;b.c\" start=\"10\" end=\"12\" symbol=\"init_queue\" symbol-kind=\"function\" context=\"queue::init_queue\"><snippet start-byte=\"120\""));
+        assert!(xml.contains("q-&gt;head = 0;"));
+        assert!(xml.contains("<block file=\"src/c.c\" start=\"30\" end=\"32\"/>"));
+    }
+
+    #[test]
+    fn test_to_xml_round_trip() {
+        let summary = summary();
+        let xml = to_xml(&reports(), &[], &[], &summary).unwrap();
+
+        let parsed: XmlDuplication = quick_xml::de::from_str(&xml).unwrap();
+        assert_eq!(parsed, XmlDuplication::new(&reports(), &[], &[], &summary));
+
+        let empty = to_xml(&[], &[], &[], &Summary::default()).unwrap();
+        let parsed: XmlDuplication = quick_xml::de::from_str(&empty).unwrap();
+        assert!(parsed.clones.is_empty());
+    }
+
+    #[test]
+    fn test_to_xml_baseline() {
+        let mut current = reports();
+        current[0].baseline_status = Some(BaselineStatus::New);
+        let mut resolved = reports();
+        resolved[0].fingerprint = "0badc0de".to_string();
+        resolved[0].baseline_status = Some(BaselineStatus::Resolved);
+        let summary = Summary {
+            baseline: Some(BaselineSummary::new(&current, &resolved)),
+            ..summary()
+        };
+
+        let xml = to_xml(&current, &resolved, &[], &summary).unwrap();
+        assert!(xml.contains("ratio=\"0.06\" new=\"1\" existing=\"0\" resolved=\"1\">"));
+        assert!(xml.contains("similarity=\"0.875\" status=\"new\">"));
+        assert!(xml.contains(
+            "</clone><resolved fingerprint=\"0badc0de\" type=\"renamed\" lines=\"3\" similarity=\"0.875\" status=\"resolved\">"
+        ));
+
+        let parsed: XmlDuplication = quick_xml::de::from_str(&xml).unwrap();
+        assert_eq!(
+            parsed,
+            XmlDuplication::new(&current, &resolved, &[], &summary)
+        );
+    }
+
+    #[test]
+    fn test_to_xml_suppressed() {
+        let summary = Summary {
+            suppressed_blocks: 2,
+            ..summary()
+        };
+        let xml = to_xml(&[], &[], &reports(), &summary).unwrap();
+        assert!(xml.contains("ratio=\"0.06\" suppressed=\"2\">"));
+        assert!(xml.contains("</summary><suppressed fingerprint=\"f40bd297\""));
+
+        let parsed: XmlDuplication = quick_xml::de::from_str(&xml).unwrap();
+        assert_eq!(parsed, XmlDuplication::new(&[], &[], &reports(), &summary));
+    }
+
+    #[test]
+    fn test_schema_declares_report_elements() {
+        let mut reader = Reader::from_str(SCHEMA);
+        let mut elements = Vec::new();
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(element) | Event::Empty(element)
+                    if element.name().as_ref() == b"xs:element" =>
+                {
+                    let name = element
+                        .try_get_attribute("name")
+                        .unwrap()
+                        .map(|attribute| attribute.unescape_value().unwrap().to_string());
+                    elements.extend(name);
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        assert_eq!(
+            elements,
+            vec![
+                "duplication",
+                "summary",
+                "file",
+                "directory",
+                "language",
+                "clone",
+                "resolved",
+                "suppressed",
+                "block",
+                "snippet"
+            ]
+        );
+    }
+}
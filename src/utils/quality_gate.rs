@@ -0,0 +1,108 @@
+use crate::models::report::Summary;
+
+/// Limits that make a run fail, set with the `--fail-on-*` options
+#[derive(Debug, Default, PartialEq)]
+pub struct QualityGate {
+    /// Most lines that may be covered by duplicate blocks
+    pub max_duplicate_lines: Option<usize>,
+    /// Highest share of duplicated lines, in percent
+    pub max_percentage: Option<f64>,
+    /// Fail when a clone class is not in the baseline
+    pub fail_on_new: bool,
+}
+
+impl QualityGate {
+    /// Describe every limit the report exceeds; an empty list means the gate passed
+    ///
+    /// Duplicated lines are counted once however many blocks cover them, as in
+    /// `uniqueDuplicateLines`.
+    pub fn failures(&self, summary: &Summary) -> Vec<String> {
+        let mut failures = Vec::new();
+        if let Some(max) = self.max_duplicate_lines {
+            if summary.unique_duplicate_lines > max {
+                failures.push(format!(
+                    "{} duplicated lines, above the limit of {}",
+                    summary.unique_duplicate_lines, max
+                ));
+            }
+        }
+        if let Some(max) = self.max_percentage {
+            let percentage = summary.duplication_ratio * 100.0;
+            if percentage > max {
+                failures.push(format!(
+                    "{:.2}% of lines duplicated, above the limit of {}%",
+                    percentage, max
+                ));
+            }
+        }
+        if self.fail_on_new {
+            let new = summary.baseline.as_ref().map_or(0, |baseline| baseline.new);
+            if new > 0 {
+                failures.push(format!("{} new clone classes since the baseline", new));
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::report::BaselineSummary;
+
+    fn summary() -> Summary {
+        Summary {
+            total_lines: 400,
+            unique_duplicate_lines: 30,
+            duplication_ratio: 0.075,
+            baseline: Some(BaselineSummary {
+                new: 2,
+                existing: 3,
+                resolved: 0,
+            }),
+            ..Summary::default()
+        }
+    }
+
+    #[test]
+    fn test_failures_without_limits() {
+        assert!(QualityGate::default().failures(&summary()).is_empty());
+    }
+
+    #[test]
+    fn test_failures_at_limits() {
+        let gate = QualityGate {
+            max_duplicate_lines: Some(30),
+            max_percentage: Some(7.5),
+            fail_on_new: false,
+        };
+        assert!(gate.failures(&summary()).is_empty());
+    }
+
+    #[test]
+    fn test_failures_above_limits() {
+        let gate = QualityGate {
+            max_duplicate_lines: Some(29),
+            max_percentage: Some(5.0),
+            fail_on_new: true,
+        };
+        assert_eq!(
+            gate.failures(&summary()),
+            vec![
+                "30 duplicated lines, above the limit of 29",
+                "7.50% of lines duplicated, above the limit of 5%",
+                "2 new clone classes since the baseline",
+            ]
+        );
+
+        let without_new = Summary {
+            baseline: Some(BaselineSummary::default()),
+            ..summary()
+        };
+        let gate = QualityGate {
+            fail_on_new: true,
+            ..QualityGate::default()
+        };
+        assert!(gate.failures(&without_new).is_empty());
+    }
+}
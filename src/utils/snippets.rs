@@ -0,0 +1,134 @@
+use crate::models::detection_types::{DuplicateReport, Snippet};
+use std::collections::HashMap;
+use std::fs;
+
+/// 1-based character column of a byte offset within its line
+fn column(source: &str, byte: usize) -> usize {
+    let line_start = source[..byte].rfind('\n').map_or(0, |index| index + 1);
+    source[line_start..byte].chars().count() + 1
+}
+
+/// Cut the source of a byte range out of its file, keeping at most `max_lines` lines
+pub fn build_snippet(
+    source: &str,
+    start_byte: usize,
+    end_byte: usize,
+    max_lines: Option<usize>,
+) -> Snippet {
+    let text = &source[start_byte..end_byte];
+    let kept = match max_lines {
+        Some(max_lines) => text
+            .match_indices('\n')
+            .nth(max_lines.saturating_sub(1))
+            .map_or(text, |(index, _)| &text[..index]),
+        None => text,
+    };
+
+    Snippet {
+        start_byte,
+        end_byte,
+        start_column: column(source, start_byte),
+        end_column: column(source, end_byte),
+        text: kept.to_string(),
+        truncated: kept.len() < text.len(),
+    }
+}
+
+/// Fill in the snippet of every block whose byte range is known, reading each file once
+///
+/// Blocks of files that can no longer be read, or whose range falls outside the file,
+/// are left without a snippet.
+pub fn attach_snippets(reports: &mut [DuplicateReport], max_lines: Option<usize>) {
+    let mut sources: HashMap<String, Option<String>> = HashMap::new();
+
+    for block in reports
+        .iter_mut()
+        .flat_map(|report| report.blocks.iter_mut())
+    {
+        let Some((start_byte, end_byte)) = block.byte_range else {
+            continue;
+        };
+        let source = sources
+            .entry(block.source_file.clone())
+            .or_insert_with(|| fs::read_to_string(&block.source_file).ok());
+        block.snippet = source
+            .as_deref()
+            .filter(|source| source.get(start_byte..end_byte).is_some())
+            .map(|source| build_snippet(source, start_byte, end_byte, max_lines));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::code_types::CloneType;
+    use crate::models::detection_types::DuplicateBlock;
+    use crate::utils::files::{create_temp_file, delete_temp_file};
+
+    const SOURCE: &str = "int main() {\n    int été = 1;\n    return été;\n}\n";
+
+    #[test]
+    fn test_build_snippet() {
+        let start_byte = SOURCE.find("int été").unwrap();
+        let end_byte = SOURCE.find("}").unwrap() + 1;
+
+        let snippet = build_snippet(SOURCE, start_byte, end_byte, None);
+        assert_eq!(snippet.start_column, 5);
+        assert_eq!(snippet.end_column, 2);
+        assert_eq!(snippet.text, &SOURCE[start_byte..end_byte]);
+        assert!(!snippet.truncated);
+
+        let return_byte = SOURCE.find(';').unwrap() + 1;
+        let snippet = build_snippet(SOURCE, start_byte, return_byte, None);
+        // Columns count characters, not bytes
+        assert_eq!(snippet.end_column, 17);
+    }
+
+    #[test]
+    fn test_build_snippet_truncates_lines() {
+        let snippet = build_snippet(SOURCE, 0, SOURCE.len(), Some(2));
+        assert_eq!(snippet.text, "int main() {\n    int été = 1;");
+        assert!(snippet.truncated);
+
+        let snippet = build_snippet(SOURCE, 0, SOURCE.len(), Some(10));
+        assert_eq!(snippet.text, SOURCE);
+        assert!(!snippet.truncated);
+    }
+
+    #[test]
+    fn test_attach_snippets() {
+        let file_path = create_temp_file(SOURCE, "c");
+        let block = |byte_range| DuplicateBlock {
+            start_line_number: 1,
+            end_line_number: 4,
+            source_file: file_path.to_string_lossy().to_string(),
+            symbol_name: None,
+            symbol_kind: None,
+            context: None,
+            snippet: None,
+            byte_range,
+        };
+        let mut reports = vec![DuplicateReport {
+            fingerprint: "f".to_string(),
+            clone_type: CloneType::Parameterized,
+            line_count: 4,
+            similarity: None,
+            baseline_status: None,
+            blocks: vec![
+                block(Some((0, SOURCE.len()))),
+                block(None),
+                block(Some((0, SOURCE.len() + 10))),
+            ],
+        }];
+
+        attach_snippets(&mut reports, None);
+        let snippets: Vec<Option<&str>> = reports[0]
+            .blocks
+            .iter()
+            .map(|block| block.snippet.as_ref().map(|snippet| snippet.text.as_str()))
+            .collect();
+        assert_eq!(snippets, vec![Some(SOURCE), None, None]);
+
+        delete_temp_file(&file_path);
+    }
+}
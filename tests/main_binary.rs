@@ -48,4 +48,62 @@ fn test_binary_entrypoint_with_valid_args() {
 
     assert!(json.get("summary").is_some(), "summary field is missing");
     assert!(json.get("records").is_some(), "records field is missing");
+
+    let report: reson::models::report::Report =
+        serde_json::from_str(&output_content).expect("output should load as a typed report");
+    assert_eq!(report.metadata.tool_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(report.metadata.options.languages, vec!["rust".to_string()]);
+}
+
+/// Run the binary on the C samples, which contain duplicates, and return its exit code
+fn exit_code_on_c_samples(extra_args: &[&str]) -> (Option<i32>, String) {
+    let manifest_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let output_file = NamedTempFile::new().expect("failed to create temp output file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_reson"))
+        .current_dir(&manifest_dir)
+        .args([
+            "--source-path",
+            "tests/c",
+            "--threads",
+            "1",
+            "--output-file",
+        ])
+        .arg(output_file.path())
+        .args(extra_args)
+        .output()
+        .expect("failed to execute reson binary");
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn test_binary_exit_codes() {
+    let (code, stderr) = exit_code_on_c_samples(&["--fail-on-duplicate-lines", "100000"]);
+    assert_eq!(code, Some(0), "stderr: {}", stderr);
+
+    let (code, stderr) = exit_code_on_c_samples(&["--fail-on-duplicate-lines", "0"]);
+    assert_eq!(code, Some(1), "stderr: {}", stderr);
+    assert!(stderr.contains("Quality gate failed"));
+
+    let (code, _) = exit_code_on_c_samples(&["--fail-on-percentage", "0"]);
+    assert_eq!(code, Some(1));
+
+    let (code, _) = exit_code_on_c_samples(&["--fail-on-percentage", "not-a-number"]);
+    assert_eq!(code, Some(2));
+
+    let (code, stderr) = exit_code_on_c_samples(&["--baseline", "missing/baseline.json"]);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("Cannot read baseline"));
+}
+
+#[test]
+fn test_binary_exit_code_on_missing_source_path() {
+    let output = Command::new(env!("CARGO_BIN_EXE_reson"))
+        .args(["--source-path", "missing/source/path"])
+        .output()
+        .expect("failed to execute reson binary");
+    assert_eq!(output.status.code(), Some(2));
 }